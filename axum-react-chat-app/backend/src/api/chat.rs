@@ -8,7 +8,7 @@ use axum::{
     },
     Json,
 };
-use futures_util::stream::StreamExt;
+use futures_util::stream::{self, StreamExt};
 use serde_json::json;
 use tokio::sync::broadcast;
 use tokio_stream::wrappers::BroadcastStream;
@@ -17,6 +17,7 @@ use sea_orm::{
     ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
 };
 
+use super::state::Shutdown;
 use crate::entities::{
     chat::{ActiveModel as ActiveChat, Column, Entity as ChatEntity, Model as Chat},
     room::{ActiveModel as ActiveRoom, Entity as RoomEntity},
@@ -24,13 +25,21 @@ use crate::entities::{
 
 pub async fn subscribe(
     State(queue): State<broadcast::Sender<Chat>>,
+    State(shutdown): State<Shutdown>,
 ) -> impl IntoResponse {
-    let stream = BroadcastStream::new(queue.subscribe()).map(|msg| match msg {
-        Ok(msg) => Ok(Event::default()
-            .event("message")
-            .data(json!(msg).to_string())),
-        Err(e) => Err(e),
-    });
+    let stream = BroadcastStream::new(queue.subscribe())
+        .map(|msg| match msg {
+            Ok(msg) => Ok(Event::default()
+                .event("message")
+                .data(json!(msg).to_string())),
+            Err(e) => Err(e),
+        })
+        // on shutdown, end the stream with a `close` event so clients know
+        // to reconnect instead of waiting on a dead connection
+        .take_until(async move { shutdown.wait().await })
+        .chain(stream::once(async {
+            Ok(Event::default().event("close").data("server shutting down"))
+        }));
 
     Sse::new(stream).keep_alive(KeepAlive::default())
 }
@@ -0,0 +1,20 @@
+use axum::{extract::State, http::StatusCode, Json};
+use sea_orm::DatabaseConnection;
+use serde_json::{json, Value};
+
+pub async fn healthz() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+pub async fn readyz(State(conn): State<DatabaseConnection>) -> (StatusCode, Json<Value>) {
+    match conn.ping().await {
+        Ok(()) => (StatusCode::OK, Json(json!({ "status": "ready" }))),
+        Err(e) => {
+            tracing::warn!("Readiness check failed: {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "status": "unavailable" })),
+            )
+        }
+    }
+}
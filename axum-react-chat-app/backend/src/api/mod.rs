@@ -1,4 +1,6 @@
 pub mod chat;
 pub mod chat_room;
+pub mod health;
+pub mod rate_limit;
 pub mod state;
 pub mod user;
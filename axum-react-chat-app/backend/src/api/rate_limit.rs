@@ -0,0 +1,127 @@
+use std::{
+    env, fmt,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header::RETRY_AFTER, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+pub use rate_limiter::{InMemoryStore, Quota, RateLimitStore};
+use serde_json::json;
+
+pub const DEFAULT_CHAT_QUOTA: Quota = Quota {
+    burst: 20,
+    per_minute: 60,
+};
+pub const DEFAULT_API_QUOTA: Quota = Quota {
+    burst: 60,
+    per_minute: 300,
+};
+
+#[derive(Debug)]
+pub struct QuotaError(String);
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid rate limit: {}", self.0)
+    }
+}
+
+impl std::error::Error for QuotaError {}
+
+// `{prefix}_RATE_LIMIT_BURST` and `{prefix}_RATE_LIMIT_PER_MINUTE`, e.g.
+// CHAT_RATE_LIMIT_BURST=20, falling back to `default`
+pub fn quota_from_env(prefix: &str, default: Quota) -> Result<Quota, QuotaError> {
+    let var = |name: &str, default: u32| {
+        let name = format!("{}_RATE_LIMIT_{}", prefix, name);
+        match env::var(&name) {
+            Ok(value) => match value.parse() {
+                Ok(value) if value > 0 => Ok(value),
+                _ => Err(QuotaError(format!(
+                    "{} must be a positive integer, got `{}`",
+                    name, value
+                ))),
+            },
+            Err(_) => Ok(default),
+        }
+    };
+
+    Ok(Quota {
+        burst: var("BURST", default.burst)?,
+        per_minute: var("PER_MINUTE", default.per_minute)?,
+    })
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    inner: rate_limiter::RateLimiter,
+    behind_proxy: bool,
+}
+
+impl RateLimiter {
+    // `behind_proxy`: take the client address from the hop the platform proxy
+    // appended to X-Forwarded-For (shuttle) instead of the peer address
+    pub fn new(
+        group: &'static str,
+        quota: Quota,
+        store: Arc<dyn RateLimitStore>,
+        behind_proxy: bool,
+    ) -> Self {
+        Self {
+            inner: rate_limiter::RateLimiter::new(group, quota, store),
+            behind_proxy,
+        }
+    }
+}
+
+fn client_ip(request: &Request<Body>, behind_proxy: bool) -> Option<IpAddr> {
+    if behind_proxy {
+        // earlier entries are whatever the client sent
+        return request
+            .headers()
+            .get("x-forwarded-for")?
+            .to_str()
+            .ok()?
+            .rsplit(',')
+            .next()?
+            .trim()
+            .parse()
+            .ok();
+    }
+
+    let ConnectInfo(addr) = request.extensions().get::<ConnectInfo<SocketAddr>>()?;
+    Some(addr.ip())
+}
+
+// token bucket per client IP; requests without a known address share the
+// `unknown` bucket. `sender` is chosen by the client, so it is not a key
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let client = match client_ip(&request, limiter.behind_proxy) {
+        Some(ip) => format!("ip:{}", ip),
+        None => "ip:unknown".to_owned(),
+    };
+    let decision = limiter.inner.acquire(&client).await;
+
+    let mut response = match decision.retry_after {
+        Some(retry_after) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, rate_limiter::seconds(retry_after))],
+            Json(json!({ "error": "rate limit exceeded" })),
+        )
+            .into_response(),
+        None => next.run(request).await,
+    };
+    decision.set_headers(response.headers_mut());
+
+    response
+}
@@ -1,11 +1,38 @@
+use std::sync::Arc;
+
 use crate::entities::chat::Model as Chat;
 
 use axum::extract::FromRef;
 use sea_orm::DatabaseConnection;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
     pub conn: DatabaseConnection,
     pub queue: broadcast::Sender<Chat>,
+    pub shutdown: Shutdown,
+}
+
+/// Tells long-lived responses such as the SSE subscription that the server is
+/// going down.
+#[derive(Clone)]
+pub struct Shutdown(Arc<watch::Sender<bool>>);
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(false).0))
+    }
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Resolves once `trigger` has been called.
+    pub async fn wait(&self) {
+        let mut rx = self.0.subscribe();
+        // The sender lives in `self`, so the channel cannot close while we wait.
+        let _ = rx.wait_for(|shutting_down| *shutting_down).await;
+    }
 }
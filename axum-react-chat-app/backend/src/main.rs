@@ -1,21 +1,30 @@
 mod api;
 mod entities;
+mod server;
 
 use api::{
     chat::{get_chat, send, subscribe},
     chat_room::{delete_room, get_room, post_room, put_room},
-    state::AppState,
+    health::{healthz, readyz},
+    rate_limit::{
+        quota_from_env, rate_limit, InMemoryStore, RateLimitStore, RateLimiter, DEFAULT_API_QUOTA,
+        DEFAULT_CHAT_QUOTA,
+    },
+    state::{AppState, Shutdown},
     user::{delete_user, get_user, post_user, put_user},
 };
 
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
+use shuttle_runtime::CustomError;
 
 use migration::{Migrator, MigratorTrait};
 use sea_orm::SqlxPostgresConnector;
 use sqlx::PgPool;
+use std::{net::SocketAddr, sync::Arc};
 use tokio::sync::broadcast;
 use tower_http::{
     cors::{Any, CorsLayer},
@@ -23,8 +32,31 @@ use tower_http::{
 };
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+/// Serves like `shuttle_axum::AxumService`, but shuts down gracefully so
+/// subscribers get the `close` event when a deployment is replaced.
+struct ChatService {
+    app: Router,
+    shutdown: Shutdown,
+}
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for ChatService {
+    async fn bind(mut self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(CustomError::new)?;
+        server::serve(listener, self.app, self.shutdown)
+            .await
+            .map_err(CustomError::new)?;
+
+        Ok(())
+    }
+}
+
 #[shuttle_runtime::main]
-async fn main(#[shuttle_shared_db::Postgres] pool: PgPool) -> shuttle_axum::ShuttleAxum {
+async fn main(
+    #[shuttle_shared_db::Postgres] pool: PgPool,
+) -> Result<ChatService, shuttle_runtime::Error> {
     dotenvy::dotenv().ok();
 
     tracing_subscriber::registry()
@@ -32,34 +64,54 @@ async fn main(#[shuttle_shared_db::Postgres] pool: PgPool) -> shuttle_axum::Shut
         .with(EnvFilter::from_default_env())
         .init();
 
+    let shutdown = Shutdown::default();
     let state = AppState {
         conn: SqlxPostgresConnector::from_sqlx_postgres_pool(pool),
         queue: broadcast::channel(10).0,
+        shutdown: shutdown.clone(),
     };
 
     Migrator::up(&state.conn, None).await.unwrap();
 
+    let store: Arc<dyn RateLimitStore> = Arc::new(InMemoryStore::default());
+    let limiter = |group, quota| RateLimiter::new(group, quota, store.clone(), true);
+    let chat_limiter = limiter(
+        "chat",
+        quota_from_env("CHAT", DEFAULT_CHAT_QUOTA).map_err(CustomError::new)?,
+    );
+    let api_limiter = limiter(
+        "api",
+        quota_from_env("API", DEFAULT_API_QUOTA).map_err(CustomError::new)?,
+    );
+
     let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
         .nest(
             "/chat",
             Router::new()
                 .route("/", get(get_chat))
                 .route("/subscribe", get(subscribe))
-                .route("/send", post(send)),
+                .route("/send", post(send))
+                .route_layer(middleware::from_fn_with_state(chat_limiter, rate_limit)),
         )
-        .route(
-            "/room",
-            get(get_room)
-                .post(post_room)
-                .put(put_room)
-                .delete(delete_room),
-        )
-        .route(
-            "/user",
-            get(get_user)
-                .post(post_user)
-                .put(put_user)
-                .delete(delete_user),
+        .merge(
+            Router::new()
+                .route(
+                    "/room",
+                    get(get_room)
+                        .post(post_room)
+                        .put(put_room)
+                        .delete(delete_room),
+                )
+                .route(
+                    "/user",
+                    get(get_user)
+                        .post(post_user)
+                        .put(put_user)
+                        .delete(delete_user),
+                )
+                .route_layer(middleware::from_fn_with_state(api_limiter, rate_limit)),
         )
         .layer(
             CorsLayer::new()
@@ -73,5 +125,5 @@ async fn main(#[shuttle_shared_db::Postgres] pool: PgPool) -> shuttle_axum::Shut
         )
         .with_state(state);
 
-    Ok(app.into())
+    Ok(ChatService { app, shutdown })
 }
@@ -1,16 +1,25 @@
 mod api;
 mod db;
 mod entities;
+mod server;
 
 use api::{
     chat::{get_chat, send, subscribe},
     chat_room::{delete_room, get_room, post_room, put_room},
-    state::AppState,
+    health::{healthz, readyz},
+    rate_limit::{
+        quota_from_env, rate_limit, InMemoryStore, RateLimitStore, RateLimiter, DEFAULT_API_QUOTA,
+        DEFAULT_CHAT_QUOTA,
+    },
+    state::{AppState, Shutdown},
     user::{delete_user, get_user, post_user, put_user},
 };
 use db::init::init_db;
 
+use std::sync::Arc;
+
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -26,37 +35,62 @@ use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 async fn main() {
     dotenvy::dotenv().ok();
 
+    let (chat_quota, api_quota) = match (
+        quota_from_env("CHAT", DEFAULT_CHAT_QUOTA),
+        quota_from_env("API", DEFAULT_API_QUOTA),
+    ) {
+        (Ok(chat), Ok(api)) => (chat, api),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
     tracing_subscriber::registry()
         .with(fmt::layer())
         .with(EnvFilter::from_default_env())
         .init();
 
+    let shutdown = Shutdown::default();
     let state = AppState {
         conn: init_db().await,
         queue: broadcast::channel(10).0,
+        shutdown: shutdown.clone(),
     };
 
+    let store: Arc<dyn RateLimitStore> = Arc::new(InMemoryStore::default());
+    let limiter = |group, quota| RateLimiter::new(group, quota, store.clone(), false);
+    let chat_limiter = limiter("chat", chat_quota);
+    let api_limiter = limiter("api", api_quota);
+
     let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
         .nest(
             "/chat",
             Router::new()
                 .route("/", get(get_chat))
                 .route("/subscribe", get(subscribe))
-                .route("/send", post(send)),
-        )
-        .route(
-            "/room",
-            get(get_room)
-                .post(post_room)
-                .put(put_room)
-                .delete(delete_room),
+                .route("/send", post(send))
+                .route_layer(middleware::from_fn_with_state(chat_limiter, rate_limit)),
         )
-        .route(
-            "/user",
-            get(get_user)
-                .post(post_user)
-                .put(put_user)
-                .delete(delete_user),
+        .merge(
+            Router::new()
+                .route(
+                    "/room",
+                    get(get_room)
+                        .post(post_room)
+                        .put(put_room)
+                        .delete(delete_room),
+                )
+                .route(
+                    "/user",
+                    get(get_user)
+                        .post(post_user)
+                        .put(put_user)
+                        .delete(delete_user),
+                )
+                .route_layer(middleware::from_fn_with_state(api_limiter, rate_limit)),
         )
         .layer(
             CorsLayer::new()
@@ -71,5 +105,5 @@ async fn main() {
         .with_state(state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    server::serve(listener, app, shutdown).await.unwrap();
 }
@@ -0,0 +1,58 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::Router;
+use tokio::{net::TcpListener, signal};
+
+use crate::api::state::Shutdown;
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Serves `app` until SIGINT or SIGTERM, then closes subscriptions through
+/// `shutdown` and gives in-flight requests a bounded time to finish.
+pub async fn serve(listener: TcpListener, app: Router, shutdown: Shutdown) -> std::io::Result<()> {
+    let trigger = shutdown.clone();
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        tracing::info!("Shutting down, closing subscriptions...");
+        trigger.trigger();
+    });
+
+    tokio::select! {
+        result = async { server.await } => result,
+        _ = async {
+            shutdown.wait().await;
+            tokio::time::sleep(SHUTDOWN_TIMEOUT).await;
+        } => {
+            tracing::warn!("Drain timeout elapsed, dropping remaining connections");
+            Ok(())
+        }
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
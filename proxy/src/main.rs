@@ -1,12 +1,23 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
 use reqwest::Client;
 use serde::Deserialize;
-use axum::{body::Bytes, extract::State, http::StatusCode, routing::post, Json, Router};
+use tokio::{signal, sync::oneshot};
+use tracing::{info, warn};
 
 type Cache = Arc<Mutex<HashMap<String, Bytes>>>;
 
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Deserialize)]
 struct Data {
     // 견종
@@ -45,13 +56,71 @@ async fn proxy_handler(State(state): State<Cache>, Json(data): Json<Data>) -> (S
 
 #[tokio::main]
 async fn main() {
+    tracing_subscriber::fmt::init();
+
     let state: Cache = Arc::new(Mutex::new(HashMap::new()));
     let app = Router::new()
         .route("/", post(proxy_handler))
+        .route("/healthz", get(health))
+        .route("/readyz", get(health))
         .with_state(state);
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:8000")
         .await
         .unwrap();
-    axum::serve(listener, app).await.unwrap();
+
+    let (signalled_tx, signalled_rx) = oneshot::channel();
+
+    let server = async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                shutdown_signal().await;
+                info!("Shutting down, draining in-flight requests...");
+                let _ = signalled_tx.send(());
+            })
+            .await
+            .unwrap();
+    };
+
+    // Requests still running after the timeout are dropped.
+    let deadline = async move {
+        if signalled_rx.await.is_ok() {
+            tokio::time::sleep(SHUTDOWN_TIMEOUT).await;
+        } else {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    tokio::select! {
+        _ = server => info!("Server stopped"),
+        _ = deadline => warn!("Drain timeout elapsed, dropping remaining connections"),
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+async fn health() -> &'static str {
+    "ok"
 }
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    async_trait,
+    http::{HeaderMap, HeaderName, HeaderValue},
+};
+use serde::Deserialize;
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// Buckets are pruned once the in-memory store holds this many keys.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Quota {
+    /// Requests that may be made at once.
+    pub burst: u32,
+    /// Sustained requests per minute.
+    pub per_minute: u32,
+}
+
+/// Outcome of taking one token from a bucket.
+pub struct Decision {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until the bucket is full again.
+    pub reset: Duration,
+    /// Time until the next token; set only when the request is rejected.
+    pub retry_after: Option<Duration>,
+}
+
+impl Decision {
+    /// Adds the `RateLimit-Limit`, `-Remaining` and `-Reset` headers.
+    pub fn set_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(RATELIMIT_LIMIT, HeaderValue::from(self.limit));
+        headers.insert(RATELIMIT_REMAINING, HeaderValue::from(self.remaining));
+        headers.insert(RATELIMIT_RESET, seconds(self.reset));
+    }
+}
+
+/// Whole seconds, rounded up, as `Retry-After` and `RateLimit-Reset` expect.
+pub fn seconds(duration: Duration) -> HeaderValue {
+    HeaderValue::from(duration.as_secs_f64().ceil() as u64)
+}
+
+/// Where buckets live; implement this to share limits between instances,
+/// e.g. on top of Redis.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn acquire(&self, key: &str, quota: Quota) -> Decision;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    full_at: Instant,
+}
+
+/// Process-local token buckets.
+#[derive(Default)]
+pub struct InMemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+fn refill_rate(quota: Quota) -> f64 {
+    f64::from(quota.per_minute) / 60.0
+}
+
+fn refill(bucket: &mut Bucket, quota: Quota, now: Instant) {
+    let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * refill_rate(quota)).min(f64::from(quota.burst));
+    bucket.updated = now;
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryStore {
+    async fn acquire(&self, key: &str, quota: Quota) -> Decision {
+        let now = Instant::now();
+        let capacity = f64::from(quota.burst);
+        let rate = refill_rate(quota);
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            // A full bucket is the same as no bucket.
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            full_at: now,
+        });
+        refill(bucket, quota, now);
+
+        let retry_after = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        };
+        let reset = Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+        bucket.full_at = now + reset;
+
+        Decision {
+            limit: quota.burst,
+            remaining: bucket.tokens.floor() as u32,
+            reset,
+            retry_after,
+        }
+    }
+}
+
+/// A quota for one route group backed by a shared store.
+#[derive(Clone)]
+pub struct RateLimiter {
+    group: &'static str,
+    quota: Quota,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    pub fn new(group: &'static str, quota: Quota, store: Arc<dyn RateLimitStore>) -> Self {
+        Self {
+            group,
+            quota,
+            store,
+        }
+    }
+
+    /// Takes a token from `client`'s bucket in this group.
+    pub async fn acquire(&self, client: &str) -> Decision {
+        let key = format!("{}:{}", self.group, client);
+        self.store.acquire(&key, self.quota).await
+    }
+}
@@ -1,12 +1,34 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20220101_000002_create_session_table;
+mod m20220101_000003_add_role_to_users;
+mod m20220101_000004_add_product_search_indexes;
+mod m20220101_000005_create_product_image_table;
+mod m20220101_000006_add_product_stock;
+mod m20220101_000007_create_cart_and_order_tables;
+mod m20220101_000008_category_tree;
+mod m20220101_000009_soft_delete_and_audit_log;
+mod m20220101_000010_email_and_user_tokens;
+mod m20220101_000011_two_factor;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20220101_000002_create_session_table::Migration),
+            Box::new(m20220101_000003_add_role_to_users::Migration),
+            Box::new(m20220101_000004_add_product_search_indexes::Migration),
+            Box::new(m20220101_000005_create_product_image_table::Migration),
+            Box::new(m20220101_000006_add_product_stock::Migration),
+            Box::new(m20220101_000007_create_cart_and_order_tables::Migration),
+            Box::new(m20220101_000008_category_tree::Migration),
+            Box::new(m20220101_000009_soft_delete_and_audit_log::Migration),
+            Box::new(m20220101_000010_email_and_user_tokens::Migration),
+            Box::new(m20220101_000011_two_factor::Migration),
+        ]
     }
 }
@@ -0,0 +1,102 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProductImage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProductImage::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProductImage::ProductId).integer().not_null())
+                    .col(
+                        ColumnDef::new(ProductImage::StorageKey)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ProductImage::ThumbnailKey)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProductImage::ContentType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProductImage::SizeBytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ProductImage::Width).integer().not_null())
+                    .col(ColumnDef::new(ProductImage::Height).integer().not_null())
+                    .col(
+                        ColumnDef::new(ProductImage::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_product_image_product")
+                            .from(ProductImage::Table, ProductImage::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_product_image_product")
+                    .table(ProductImage::Table)
+                    .col(ProductImage::ProductId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(ProductImage::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Product {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ProductImage {
+    Table,
+    Id,
+    ProductId,
+    StorageKey,
+    ThumbnailKey,
+    ContentType,
+    SizeBytes,
+    Width,
+    Height,
+    CreatedAt,
+}
@@ -0,0 +1,167 @@
+use sea_orm_migration::{prelude::*, sea_orm::DbBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .add_column(
+                        ColumnDef::new(Product::Stock)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Adjustments already refuse to go below zero; this catches anything else.
+        // SQLite can't add constraints to an existing table.
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .get_connection()
+                .execute_unprepared(
+                    "ALTER TABLE product ADD CONSTRAINT chk_product_stock CHECK (stock >= 0)",
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_product_stock")
+                    .table(Product::Table)
+                    .col(Product::Stock)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(StockMovement::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(StockMovement::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(StockMovement::ProductId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(StockMovement::Quantity).integer().not_null())
+                    .col(ColumnDef::new(StockMovement::Reason).string().not_null())
+                    .col(ColumnDef::new(StockMovement::Note).string())
+                    .col(
+                        ColumnDef::new(StockMovement::StockAfter)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(StockMovement::UserId).integer())
+                    .col(
+                        ColumnDef::new(StockMovement::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_stock_movement_product")
+                            .from(StockMovement::Table, StockMovement::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_stock_movement_user")
+                            .from(StockMovement::Table, StockMovement::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_stock_movement_product")
+                    .table(StockMovement::Table)
+                    .col(StockMovement::ProductId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(StockMovement::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_product_stock")
+                    .table(Product::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        if manager.get_database_backend() == DbBackend::Postgres {
+            manager
+                .get_connection()
+                .execute_unprepared(
+                    "ALTER TABLE product DROP CONSTRAINT IF EXISTS chk_product_stock",
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Product::Table)
+                    .drop_column(Product::Stock)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Product {
+    Table,
+    Id,
+    Stock,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum StockMovement {
+    Table,
+    Id,
+    ProductId,
+    Quantity,
+    Reason,
+    Note,
+    StockAfter,
+    UserId,
+    CreatedAt,
+}
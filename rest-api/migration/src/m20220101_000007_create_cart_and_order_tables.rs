@@ -0,0 +1,203 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CartItem::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CartItem::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CartItem::UserId).integer().not_null())
+                    .col(ColumnDef::new(CartItem::ProductId).integer().not_null())
+                    .col(ColumnDef::new(CartItem::Quantity).integer().not_null())
+                    .col(ColumnDef::new(CartItem::AddedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_cart_item_user")
+                            .from(CartItem::Table, CartItem::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_cart_item_product")
+                            .from(CartItem::Table, CartItem::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_cart_item_user_product")
+                    .table(CartItem::Table)
+                    .col(CartItem::UserId)
+                    .col(CartItem::ProductId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Orders outlive the accounts and products they refer to.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Orders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Orders::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Orders::UserId).integer())
+                    .col(ColumnDef::new(Orders::Status).string().not_null())
+                    .col(ColumnDef::new(Orders::Total).big_integer().not_null())
+                    .col(ColumnDef::new(Orders::CreatedAt).timestamp().not_null())
+                    .col(ColumnDef::new(Orders::UpdatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_orders_user")
+                            .from(Orders::Table, Orders::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_orders_user")
+                    .table(Orders::Table)
+                    .col(Orders::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderLine::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderLine::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderLine::OrderId).integer().not_null())
+                    .col(ColumnDef::new(OrderLine::ProductId).integer())
+                    .col(ColumnDef::new(OrderLine::Title).string().not_null())
+                    .col(ColumnDef::new(OrderLine::UnitPrice).integer().not_null())
+                    .col(ColumnDef::new(OrderLine::Quantity).integer().not_null())
+                    .col(
+                        ColumnDef::new(OrderLine::LineTotal)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_line_order")
+                            .from(OrderLine::Table, OrderLine::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_line_product")
+                            .from(OrderLine::Table, OrderLine::ProductId)
+                            .to(Product::Table, Product::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_order_line_order")
+                    .table(OrderLine::Table)
+                    .col(OrderLine::OrderId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderLine::Table).if_exists().to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Orders::Table).if_exists().to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(CartItem::Table).if_exists().to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Product {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CartItem {
+    Table,
+    Id,
+    UserId,
+    ProductId,
+    Quantity,
+    AddedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+    UserId,
+    Status,
+    Total,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum OrderLine {
+    Table,
+    Id,
+    OrderId,
+    ProductId,
+    Title,
+    UnitPrice,
+    Quantity,
+    LineTotal,
+}
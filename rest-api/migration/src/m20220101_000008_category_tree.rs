@@ -0,0 +1,307 @@
+use std::collections::HashSet;
+
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, DbBackend, Statement},
+};
+
+/// Replaces the name primary key of `category` with a surrogate id, adds a
+/// unique slug and an optional parent, and points `product` at the id.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Lowercase ASCII words joined by hyphens, e.g. "Board Games" -> "board-games".
+fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Slugs for the existing names, numbered where two names slugify alike.
+fn unique_slugs(names: &[String]) -> Vec<String> {
+    let mut taken = HashSet::new();
+
+    names
+        .iter()
+        .map(|name| {
+            let base = match slugify(name) {
+                slug if slug.is_empty() => "category".to_owned(),
+                slug => slug,
+            };
+            let mut slug = base.clone();
+            let mut n = 2;
+            while !taken.insert(slug.clone()) {
+                slug = format!("{}-{}", base, n);
+                n += 1;
+            }
+            slug
+        })
+        .collect()
+}
+
+async fn category_names(manager: &SchemaManager<'_>, table: &str) -> Result<Vec<String>, DbErr> {
+    let conn = manager.get_connection();
+    let rows = conn
+        .query_all(Statement::from_string(
+            conn.get_database_backend(),
+            format!("SELECT name FROM {} ORDER BY name", table),
+        ))
+        .await?;
+
+    rows.iter().map(|row| row.try_get("", "name")).collect()
+}
+
+async fn execute(manager: &SchemaManager<'_>, sql: &str) -> Result<(), DbErr> {
+    manager.get_connection().execute_unprepared(sql).await?;
+    Ok(())
+}
+
+fn category_table(table: Category) -> TableCreateStatement {
+    Table::create()
+        .table(table)
+        .col(
+            ColumnDef::new(Category::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Category::Name).string().not_null())
+        .col(ColumnDef::new(Category::Slug).string().not_null())
+        .col(ColumnDef::new(Category::ParentId).integer())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_category_parent")
+                .from(table, Category::ParentId)
+                .to(Category::Table, Category::Id),
+        )
+        .to_owned()
+}
+
+fn product_table() -> TableCreateStatement {
+    Table::create()
+        .table(Product::New)
+        .col(
+            ColumnDef::new(Product::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Product::Title).string().not_null())
+        .col(ColumnDef::new(Product::Price).integer().not_null())
+        .col(ColumnDef::new(Product::CategoryId).integer().not_null())
+        .col(
+            ColumnDef::new(Product::Stock)
+                .integer()
+                .not_null()
+                .default(0),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_product_category")
+                .from(Product::New, Product::CategoryId)
+                .to(Category::Table, Category::Id),
+        )
+        .to_owned()
+}
+
+async fn create_indexes(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    for (name, table, column) in [
+        ("idx_product_price", Product::Table, Product::Price),
+        ("idx_product_category", Product::Table, Product::CategoryId),
+        ("idx_product_stock", Product::Table, Product::Stock),
+    ] {
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name(name)
+                    .table(table)
+                    .col(column)
+                    .to_owned(),
+            )
+            .await?;
+    }
+
+    manager
+        .create_index(
+            Index::create()
+                .name("idx_category_slug")
+                .table(Category::Table)
+                .col(Category::Slug)
+                .unique()
+                .to_owned(),
+        )
+        .await?;
+
+    manager
+        .create_index(
+            Index::create()
+                .name("idx_category_parent")
+                .table(Category::Table)
+                .col(Category::ParentId)
+                .to_owned(),
+        )
+        .await
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let names = category_names(manager, "category").await?;
+        let slugs = unique_slugs(&names);
+
+        if manager.get_database_backend() == DbBackend::Postgres {
+            execute(
+                manager,
+                "ALTER TABLE product DROP CONSTRAINT fk_product_category;
+                 ALTER TABLE category DROP CONSTRAINT category_pkey;
+                 ALTER TABLE category ADD COLUMN id SERIAL PRIMARY KEY;
+                 ALTER TABLE category ADD COLUMN slug VARCHAR;
+                 ALTER TABLE category ADD COLUMN parent_id INTEGER
+                     CONSTRAINT fk_category_parent REFERENCES category (id);",
+            )
+            .await?;
+
+            for (name, slug) in names.iter().zip(slugs) {
+                manager
+                    .exec_stmt(
+                        Query::update()
+                            .table(Category::Table)
+                            .value(Category::Slug, slug)
+                            .and_where(Expr::col(Category::Name).eq(name.as_str()))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+
+            execute(
+                manager,
+                "ALTER TABLE category ALTER COLUMN slug SET NOT NULL;
+                 ALTER TABLE product ADD COLUMN category_id INTEGER;
+                 UPDATE product SET category_id = category.id
+                     FROM category WHERE category.name = product.category;
+                 ALTER TABLE product ALTER COLUMN category_id SET NOT NULL;
+                 ALTER TABLE product ADD CONSTRAINT fk_product_category
+                     FOREIGN KEY (category_id) REFERENCES category (id);
+                 DROP INDEX idx_product_category;
+                 ALTER TABLE product DROP COLUMN category;",
+            )
+            .await?;
+
+            return create_indexes(manager).await;
+        }
+
+        // SQLite can't change a primary key in place, so both tables are
+        // rebuilt. Foreign keys are off meanwhile so dropping the old product
+        // table doesn't cascade into images, stock movements and carts.
+        // SeaORM keeps a single SQLite connection unless told otherwise, so
+        // the pragma covers every statement below.
+        execute(manager, "PRAGMA foreign_keys = OFF").await?;
+
+        manager.create_table(category_table(Category::New)).await?;
+        for (name, slug) in names.iter().zip(slugs) {
+            manager
+                .exec_stmt(
+                    Query::insert()
+                        .into_table(Category::New)
+                        .columns([Category::Name, Category::Slug])
+                        .values_panic([name.as_str().into(), slug.into()])
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager.create_table(product_table()).await?;
+        execute(
+            manager,
+            "INSERT INTO product_new (id, title, price, category_id, stock)
+                 SELECT product.id, product.title, product.price, category_new.id, product.stock
+                 FROM product JOIN category_new ON category_new.name = product.category;
+             DROP TABLE product;
+             DROP TABLE category;
+             ALTER TABLE category_new RENAME TO category;
+             ALTER TABLE product_new RENAME TO product;",
+        )
+        .await?;
+        create_indexes(manager).await?;
+
+        execute(manager, "PRAGMA foreign_keys = ON").await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::Postgres {
+            return execute(
+                manager,
+                "ALTER TABLE product ADD COLUMN category VARCHAR;
+                 UPDATE product SET category = category.name
+                     FROM category WHERE category.id = product.category_id;
+                 ALTER TABLE product ALTER COLUMN category SET NOT NULL;
+                 ALTER TABLE product DROP CONSTRAINT fk_product_category;
+                 DROP INDEX idx_product_category;
+                 ALTER TABLE product DROP COLUMN category_id;
+                 ALTER TABLE category DROP COLUMN parent_id;
+                 ALTER TABLE category DROP COLUMN slug;
+                 ALTER TABLE category DROP COLUMN id;
+                 ALTER TABLE category ADD PRIMARY KEY (name);
+                 ALTER TABLE product ADD CONSTRAINT fk_product_category
+                     FOREIGN KEY (category) REFERENCES category (name);
+                 CREATE INDEX idx_product_category ON product (category);",
+            )
+            .await;
+        }
+
+        execute(
+            manager,
+            "PRAGMA foreign_keys = OFF;
+             CREATE TABLE category_old (name VARCHAR NOT NULL PRIMARY KEY);
+             INSERT INTO category_old (name) SELECT DISTINCT name FROM category;
+             CREATE TABLE product_old (
+                 id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+                 title VARCHAR NOT NULL,
+                 price INTEGER NOT NULL,
+                 category VARCHAR NOT NULL,
+                 stock INTEGER NOT NULL DEFAULT 0,
+                 CONSTRAINT fk_product_category FOREIGN KEY (category) REFERENCES category (name)
+             );
+             INSERT INTO product_old (id, title, price, category, stock)
+                 SELECT product.id, product.title, product.price, category.name, product.stock
+                 FROM product JOIN category ON category.id = product.category_id;
+             DROP TABLE product;
+             DROP TABLE category;
+             ALTER TABLE category_old RENAME TO category;
+             ALTER TABLE product_old RENAME TO product;
+             CREATE INDEX idx_product_price ON product (price);
+             CREATE INDEX idx_product_category ON product (category);
+             CREATE INDEX idx_product_stock ON product (stock);
+             PRAGMA foreign_keys = ON;",
+        )
+        .await
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Category {
+    Table,
+    #[sea_orm(iden = "category_new")]
+    New,
+    Id,
+    Name,
+    Slug,
+    ParentId,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Product {
+    Table,
+    #[sea_orm(iden = "product_new")]
+    New,
+    Id,
+    Title,
+    Price,
+    CategoryId,
+    Stock,
+}
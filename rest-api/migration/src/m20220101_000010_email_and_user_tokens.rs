@@ -0,0 +1,137 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite can only add one column per ALTER TABLE.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(ColumnDef::new(Users::Email).string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(ColumnDef::new(Users::EmailVerifiedAt).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Addresses are stored lowercased; users without one don't collide.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_users_email")
+                    .table(Users::Table)
+                    .col(Users::Email)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only the SHA-256 digest of a token is stored, like session tokens.
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserToken::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserToken::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UserToken::UserId).integer().not_null())
+                    .col(ColumnDef::new(UserToken::Purpose).string().not_null())
+                    .col(
+                        ColumnDef::new(UserToken::TokenHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    // The address the token was mailed to; verifying it is void
+                    // once the user has changed their address again.
+                    .col(ColumnDef::new(UserToken::Email).string().not_null())
+                    .col(ColumnDef::new(UserToken::ExpiresAt).timestamp().not_null())
+                    .col(ColumnDef::new(UserToken::UsedAt).timestamp())
+                    .col(ColumnDef::new(UserToken::CreatedAt).timestamp().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_token_user")
+                            .from(UserToken::Table, UserToken::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_token_user")
+                    .table(UserToken::Table)
+                    .col(UserToken::UserId)
+                    .col(UserToken::Purpose)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserToken::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_users_email")
+                    .table(Users::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [Users::EmailVerifiedAt, Users::Email] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Users::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    Email,
+    EmailVerifiedAt,
+}
+
+#[derive(DeriveIden)]
+enum UserToken {
+    Table,
+    Id,
+    UserId,
+    Purpose,
+    TokenHash,
+    Email,
+    ExpiresAt,
+    UsedAt,
+    CreatedAt,
+}
@@ -0,0 +1,302 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use chrono::{Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::users::audit_change;
+use crate::{
+    config::Config,
+    entities::{
+        sea_orm_active_enums::{AuditAction, TokenPurpose},
+        users::{ActiveModel, Column, Entity, Model},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        audit::Actor,
+        extract::{Json, ValidatedJson},
+        hash::{hash_password, PasswordHasher},
+        jwt::Claims,
+        lockout::LoginLockout,
+        mailer::{send_in_background, Email, Mailer},
+        session::revoke_user_sessions,
+        user_token::{self, invalid_token},
+        validation::validate_password,
+    },
+};
+
+/// Addresses are compared and stored lowercased.
+pub(crate) fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn link(config: &Config, page: &str, token: &str) -> String {
+    format!(
+        "{}/{}?token={}",
+        config.mail.link_base_url.trim_end_matches('/'),
+        page,
+        token
+    )
+}
+
+/// Issues a verification token for the user's address and returns the mail
+/// carrying it, or `None` if there is nothing to verify.
+pub(crate) async fn verification_email<C: ConnectionTrait>(
+    conn: &C,
+    config: &Config,
+    user: &Model,
+) -> Result<Option<Email>, AppError> {
+    let Some(address) = user.email.as_deref() else {
+        return Ok(None);
+    };
+    if user.email_verified_at.is_some() {
+        return Ok(None);
+    }
+
+    let hours = config.auth.email_verification_hours;
+    let token = user_token::issue(
+        conn,
+        user.id,
+        TokenPurpose::VerifyEmail,
+        address,
+        Duration::hours(hours),
+    )
+    .await?;
+
+    Ok(Some(Email {
+        to: address.to_owned(),
+        subject: "Verify your email address".to_owned(),
+        body: format!(
+            "Hi {},\n\nplease confirm this is your email address by opening\n\n{}\n\nThe link works for {} hours.\n",
+            user.username,
+            link(config, "verify-email", &token),
+            hours
+        ),
+    }))
+}
+
+async fn find_live<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Option<Model>, AppError> {
+    Ok(Entity::find_by_id(id)
+        .filter(Column::DeletedAt.is_null())
+        .one(conn)
+        .await?)
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct TokenRequest {
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    token: Option<String>,
+}
+
+/// Confirms the address a verification mail was sent to. The link stops
+/// working once used, once a newer one was sent, and when the user changes
+/// their address.
+#[utoipa::path(
+    post,
+    path = "/auth/verify-email",
+    tag = "auth",
+    request_body = TokenRequest,
+    responses(
+        (status = 200, description = "Email address verified", body = String),
+        (status = 400, description = "Invalid, used or expired token", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+pub async fn verify_email(
+    State(conn): State<DatabaseConnection>,
+    ValidatedJson(request): ValidatedJson<TokenRequest>,
+) -> Result<Json<&'static str>, AppError> {
+    let txn = conn.begin().await?;
+    let token = user_token::consume(
+        &txn,
+        &request.token.unwrap_or_default(),
+        TokenPurpose::VerifyEmail,
+    )
+    .await?;
+
+    let user = find_live(&txn, token.user_id)
+        .await?
+        .filter(|user| user.email.as_deref() == Some(token.email.as_str()))
+        .ok_or_else(invalid_token)?;
+
+    if user.email_verified_at.is_none() {
+        let mut verified: ActiveModel = user.clone().into();
+        verified.email_verified_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+        let verified = verified.update(&txn).await?;
+        let actor = Actor::new(user.id, user.username.clone());
+        audit_change(&txn, &actor, AuditAction::Update, Some(&user), &verified).await?;
+    }
+    txn.commit().await?;
+
+    Ok(Json("Email verified"))
+}
+
+/// Mails a new verification link to the caller's address; earlier links
+/// stop working.
+#[utoipa::path(
+    post,
+    path = "/users/verification",
+    tag = "users",
+    responses(
+        (status = 200, description = "Verification mail sent", body = String),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 409, description = "No address set, or it is verified already", body = ErrorBody),
+        (status = 500, description = "The mail could not be sent", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn resend_verification(
+    State(conn): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    claims: Claims,
+) -> Result<Json<&'static str>, AppError> {
+    let user = find_live(&conn, claims.sub)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found"))?;
+
+    if user.email.is_none() {
+        return Err(
+            AppError::new(StatusCode::CONFLICT, "No email address is set")
+                .with_code("email_missing"),
+        );
+    }
+    let email = verification_email(&conn, &config, &user)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(StatusCode::CONFLICT, "Email address is already verified")
+                .with_code("email_already_verified")
+        })?;
+    mailer.send(&email).await?;
+
+    Ok(Json("Verification mail sent"))
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct ForgotPassword {
+    #[validate(required, email(message = "must be a valid email address"))]
+    email: Option<String>,
+}
+
+/// Mails a password reset link if the address belongs to an account and is
+/// verified. The response is the same either way, so it doesn't reveal whether
+/// an address is verified; signup still reports addresses that are taken.
+#[utoipa::path(
+    post,
+    path = "/auth/forgot-password",
+    tag = "auth",
+    request_body = ForgotPassword,
+    responses(
+        (status = 200, description = "Reset link mailed if the address is known", body = String),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+pub async fn forgot_password(
+    State(conn): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    ValidatedJson(request): ValidatedJson<ForgotPassword>,
+) -> Result<Json<&'static str>, AppError> {
+    let address = normalize_email(&request.email.unwrap_or_default());
+
+    // Unverified addresses may be mistyped and belong to someone else, who
+    // could then take the account over.
+    let user = Entity::find()
+        .filter(Column::Email.eq(&address))
+        .filter(Column::EmailVerifiedAt.is_not_null())
+        .filter(Column::DeletedAt.is_null())
+        .one(&conn)
+        .await?;
+
+    if let Some(user) = user {
+        let minutes = config.auth.password_reset_minutes;
+        let token = user_token::issue(
+            &conn,
+            user.id,
+            TokenPurpose::ResetPassword,
+            &address,
+            Duration::minutes(minutes),
+        )
+        .await?;
+
+        send_in_background(
+            mailer,
+            Email {
+                to: address,
+                subject: "Reset your password".to_owned(),
+                body: format!(
+                    "Hi {},\n\nto choose a new password, open\n\n{}\n\nThe link works for {} minutes. If you didn't ask for it, ignore this mail; your password stays the same.\n",
+                    user.username,
+                    link(&config, "reset-password", &token),
+                    minutes
+                ),
+            },
+        );
+    }
+
+    Ok(Json(
+        "If the address belongs to a verified account, a reset link has been sent to it",
+    ))
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct ResetPassword {
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    token: Option<String>,
+    #[validate(required, custom(function = "validate_password"))]
+    password: Option<String>,
+}
+
+/// Sets a new password with the token from a reset mail. Every session of
+/// the user is revoked and any login lockout lifted.
+#[utoipa::path(
+    post,
+    path = "/auth/reset-password",
+    tag = "auth",
+    request_body = ResetPassword,
+    responses(
+        (status = 200, description = "Password changed", body = String),
+        (status = 400, description = "Invalid, used or expired token", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+pub async fn reset_password(
+    State(conn): State<DatabaseConnection>,
+    State(hasher): State<Arc<dyn PasswordHasher>>,
+    State(lockout): State<Arc<LoginLockout>>,
+    ValidatedJson(request): ValidatedJson<ResetPassword>,
+) -> Result<Json<&'static str>, AppError> {
+    let txn = conn.begin().await?;
+    // Both fields are `required`, so the defaults are never used.
+    let token = user_token::consume(
+        &txn,
+        &request.token.unwrap_or_default(),
+        TokenPurpose::ResetPassword,
+    )
+    .await?;
+    // A link mailed to an address the account no longer has is void.
+    let user = find_live(&txn, token.user_id)
+        .await?
+        .filter(|user| user.email.as_deref() == Some(token.email.as_str()))
+        .ok_or_else(invalid_token)?;
+
+    // Hashed only for a valid token, so made-up ones cost no hashing; if it
+    // fails, dropping the transaction leaves the token unused.
+    let hashed_password = hash_password(&hasher, &request.password.unwrap_or_default()).await?;
+    let mut updated: ActiveModel = user.clone().into();
+    updated.password = ActiveValue::Set(hashed_password);
+    let updated = updated.update(&txn).await?;
+    let actor = Actor::new(user.id, user.username.clone());
+    audit_change(&txn, &actor, AuditAction::Update, Some(&user), &updated).await?;
+    revoke_user_sessions(&txn, user.id).await?;
+    txn.commit().await?;
+
+    lockout.unlock(&user.username);
+
+    Ok(Json("Password changed"))
+}
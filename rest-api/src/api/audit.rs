@@ -0,0 +1,80 @@
+use axum::extract::State;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+    entities::{
+        audit_log::{Column, Entity, Model},
+        sea_orm_active_enums::{AuditAction, AuditEntity},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        extract::{Json, Query},
+        pagination::{paginate, Page, PageParams},
+    },
+};
+
+const SORTABLE: &[(&str, Column)] = &[("id", Column::Id), ("created_at", Column::CreatedAt)];
+
+/// Filters accepted by `GET /audit`; all given filters must match.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    entity: Option<AuditEntity>,
+    /// Id of the changed record; most useful together with `entity`.
+    entity_id: Option<i32>,
+    /// Id of the user who made the change.
+    actor_id: Option<i32>,
+    action: Option<AuditAction>,
+}
+
+/// Who changed which user, category or product, and how, newest first unless
+/// `sort` is given. Requires the admin role.
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditFilter, PageParams),
+    responses(
+        (status = 200, description = "Page of audit log entries", body = Page<Model>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_audit_log(
+    State(conn): State<DatabaseConnection>,
+    Query(params): Query<AuditFilter>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<Model>>, AppError> {
+    let mut condition = Condition::all();
+
+    if let Some(entity) = params.entity {
+        condition = condition.add(Column::Entity.eq(entity));
+    }
+
+    if let Some(entity_id) = params.entity_id {
+        condition = condition.add(Column::EntityId.eq(entity_id));
+    }
+
+    if let Some(actor_id) = params.actor_id {
+        condition = condition.add(Column::ActorId.eq(actor_id));
+    }
+
+    if let Some(action) = params.action {
+        condition = condition.add(Column::Action.eq(action));
+    }
+
+    let entries = paginate(
+        &conn,
+        Entity::find().filter(condition),
+        &page,
+        SORTABLE,
+        "id:desc",
+    )
+    .await?;
+
+    Ok(Json(entries))
+}
@@ -1,35 +1,322 @@
-use crate::entities::{prelude::Users, users::Column};
-use crate::utils::app_error::AppError;
-use crate::utils::hash::verify_password;
+use crate::config::{AuthConfig, Config};
+use crate::entities::{
+    prelude::Users,
+    users::{Column, Model},
+};
+use crate::utils::app_error::{AppError, ErrorBody};
+use crate::utils::extract::{ClientIp, Json, ValidatedJson};
+use crate::utils::hash::{hash_password, verify_password, PasswordHasher};
 use crate::utils::jwt::create_token;
-use axum::http::StatusCode;
-use axum::{extract::State, Json};
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use crate::utils::lockout::LoginLockout;
+use crate::utils::metrics::record_login;
+use crate::utils::session::{create_session, revoke_session, rotate_session};
+use crate::utils::signing_keys::SigningKeys;
+use crate::utils::two_factor::{complete_challenge, create_challenge, find_challenge, verify_code};
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
+};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::warn;
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
 pub struct RequestUser {
+    #[validate(length(min = 1, message = "must not be empty"))]
     username: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
     password: String,
 }
 
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
+pub struct RefreshRequest {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    token_type: &'static str,
+    expires_in: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChallengeResponse {
+    two_factor_required: bool,
+    /// Exchanged with a code at `POST /auth/login/totp`.
+    challenge_token: String,
+    expires_in: i64,
+}
+
+/// Tokens, or a challenge for users with two-factor authentication enabled.
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Tokens(TokenResponse),
+    Challenge(ChallengeResponse),
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct TotpLogin {
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    challenge_token: Option<String>,
+    /// A code from the authenticator app or a recovery code.
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    code: Option<String>,
+}
+
+impl TokenResponse {
+    fn new(access_token: String, refresh_token: String, auth: &AuthConfig) -> Self {
+        Self {
+            access_token,
+            refresh_token,
+            token_type: "Bearer",
+            expires_in: auth.access_token_minutes * 60,
+        }
+    }
+}
+
+/// Unknown usernames and wrong passwords are indistinguishable; repeated
+/// failures per username or client IP are throttled and eventually locked out.
+/// Users with two-factor authentication get a challenge instead of tokens.
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = RequestUser,
+    responses(
+        (status = 200, description = "Access and refresh token pair, or a two-factor challenge", body = LoginResponse),
+        (status = 401, description = "Incorrect username or password", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 429, description = "Too many failed attempts, see Retry-After", body = ErrorBody),
+    )
+)]
 pub async fn login(
     State(db): State<DatabaseConnection>,
-    Json(request_user): Json<RequestUser>,
-) -> Result<String, AppError> {
+    State(config): State<Arc<Config>>,
+    State(lockout): State<Arc<LoginLockout>>,
+    State(hasher): State<Arc<dyn PasswordHasher>>,
+    State(keys): State<Arc<SigningKeys>>,
+    ClientIp(ip): ClientIp,
+    ValidatedJson(request_user): ValidatedJson<RequestUser>,
+) -> Result<Json<LoginResponse>, AppError> {
+    if let Err(err) = lockout.check(&request_user.username, ip) {
+        record_login(false);
+        return Err(err);
+    }
+
     let user = Users::find()
-        .filter(Column::Username.eq(request_user.username))
+        .filter(Column::Username.eq(&request_user.username))
+        .filter(Column::DeletedAt.is_null())
+        .one(&db)
+        .await?;
+
+    let verified = match &user {
+        Some(user) => verify_password(&request_user.password, &user.password).await?,
+        None => {
+            lockout
+                .verify_dummy(&request_user.password, &hasher)
+                .await?;
+            false
+        }
+    };
+
+    let user = match user {
+        Some(user) if verified => user,
+        _ => {
+            lockout.record_failure(&request_user.username, ip);
+            record_login(false);
+            return Err(AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "incorrect username and/or password",
+            ));
+        }
+    };
+
+    if hasher.needs_rehash(&user.password) {
+        rehash(&db, &hasher, &user, &request_user.password).await;
+    }
+
+    // The lockout isn't reset before the second step succeeds, so guessing
+    // codes stays throttled.
+    if user.totp_enabled_at.is_some() {
+        let challenge_token = create_challenge(&db, user.id, &config.auth.totp).await?;
+        return Ok(Json(LoginResponse::Challenge(ChallengeResponse {
+            two_factor_required: true,
+            challenge_token,
+            expires_in: config.auth.totp.challenge_minutes * 60,
+        })));
+    }
+
+    lockout.record_success(&user.username);
+    let access_token = create_token(&user, &keys, &config.auth)?;
+    let refresh_token = create_session(&db, user.id, &config.auth).await?;
+    record_login(true);
+
+    Ok(Json(LoginResponse::Tokens(TokenResponse::new(
+        access_token,
+        refresh_token,
+        &config.auth,
+    ))))
+}
+
+/// The second login step: exchanges the challenge from `POST /auth/login`
+/// and a code for tokens. A wrong code leaves the challenge usable until it
+/// expires, and counts as a failed login.
+#[utoipa::path(
+    post,
+    path = "/auth/login/totp",
+    tag = "auth",
+    request_body = TotpLogin,
+    responses(
+        (status = 200, description = "Access and refresh token pair", body = TokenResponse),
+        (status = 401, description = "Invalid or expired challenge, or wrong code", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+        (status = 429, description = "Too many failed attempts, see Retry-After", body = ErrorBody),
+    )
+)]
+pub async fn login_totp(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(lockout): State<Arc<LoginLockout>>,
+    State(keys): State<Arc<SigningKeys>>,
+    ClientIp(ip): ClientIp,
+    ValidatedJson(request): ValidatedJson<TotpLogin>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let challenge =
+        find_challenge(&db, request.challenge_token.as_deref().unwrap_or_default()).await?;
+    let user = Users::find_by_id(challenge.user_id)
+        .filter(Column::DeletedAt.is_null())
         .one(&db)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "User not found"))?;
+    if let Err(err) = lockout.check(&user.username, ip) {
+        record_login(false);
+        return Err(err);
+    }
+
+    let txn = db.begin().await?;
+    if !verify_code(
+        &txn,
+        &user,
+        &config.auth.totp,
+        request.code.as_deref().unwrap_or_default(),
+    )
+    .await?
+    {
+        txn.rollback().await?;
+        lockout.record_failure(&user.username, ip);
+        record_login(false);
+        return Err(
+            AppError::new(StatusCode::UNAUTHORIZED, "Invalid code").with_code("invalid_code")
+        );
+    }
+    complete_challenge(&txn, &challenge).await?;
+    txn.commit().await?;
+
+    lockout.record_success(&user.username);
+    let access_token = create_token(&user, &keys, &config.auth)?;
+    let refresh_token = create_session(&db, user.id, &config.auth).await?;
+    record_login(true);
+
+    Ok(Json(TokenResponse::new(
+        access_token,
+        refresh_token,
+        &config.auth,
+    )))
+}
+
+/// Replaces a verified password's hash with one made by the current hasher.
+/// Failing to do so doesn't fail the login; the next one tries again.
+async fn rehash(
+    db: &DatabaseConnection,
+    hasher: &Arc<dyn PasswordHasher>,
+    user: &Model,
+    password: &str,
+) {
+    let hash = match hash_password(hasher, password).await {
+        Ok(hash) => hash,
+        Err(_) => return,
+    };
+
+    // Matching the old hash leaves a password changed meanwhile alone.
+    if let Err(err) = Users::update_many()
+        .col_expr(Column::Password, Expr::value(hash))
+        .filter(Column::Id.eq(user.id))
+        .filter(Column::Password.eq(&user.password))
+        .exec(db)
         .await
-        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error"))?
-        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found"))?;
-
-    if !verify_password(&request_user.password, &user.password)? {
-        return Err(AppError::new(
-            StatusCode::UNAUTHORIZED,
-            "incorrect username and/or password",
-        ));
+    {
+        warn!("Rehashing the password of user {} failed: {}", user.id, err);
     }
+}
+
+/// Rotates a refresh token; the presented token can no longer be used.
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New access and refresh token pair", body = TokenResponse),
+        (status = 401, description = "Invalid, expired or revoked refresh token", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+pub async fn refresh(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<SigningKeys>>,
+    ValidatedJson(request): ValidatedJson<RefreshRequest>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let (user, refresh_token) = rotate_session(&db, &request.refresh_token, &config.auth).await?;
+    let access_token = create_token(&user, &keys, &config.auth)?;
+
+    Ok(Json(TokenResponse::new(
+        access_token,
+        refresh_token,
+        &config.auth,
+    )))
+}
+
+/// Revokes the session a refresh token belongs to.
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "Session revoked", body = String),
+        (status = 401, description = "Unknown refresh token", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
+pub async fn logout(
+    State(db): State<DatabaseConnection>,
+    ValidatedJson(request): ValidatedJson<RefreshRequest>,
+) -> Result<Json<&'static str>, AppError> {
+    revoke_session(&db, &request.refresh_token).await?;
+
+    Ok(Json("Logged out"))
+}
 
-    Ok(create_token(user.username.clone())?)
+/// Public keys that verify access tokens, for other services. Empty while
+/// tokens are signed with the HS256 secret.
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    tag = "auth",
+    responses((status = 200, description = "JSON Web Key Set", body = Object))
+)]
+pub async fn jwks(State(keys): State<Arc<SigningKeys>>) -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(keys.jwks().clone()),
+    )
 }
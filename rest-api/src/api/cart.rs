@@ -0,0 +1,172 @@
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::{
+    entities::{
+        cart_item::{ActiveModel, Column, Entity},
+        product,
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        extract::{Json, ValidatedJson},
+        jwt::Claims,
+    },
+};
+
+/// A cart line at the product's current price.
+#[derive(Serialize, ToSchema)]
+pub struct CartLine {
+    product_id: i32,
+    title: String,
+    unit_price: i32,
+    quantity: i32,
+    line_total: i64,
+    /// Units in stock right now; checkout fails if there are too few.
+    stock: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Cart {
+    items: Vec<CartLine>,
+    total: i64,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct SetCartItem {
+    #[validate(required)]
+    product_id: Option<i32>,
+    /// New quantity; 0 removes the product from the cart.
+    #[validate(
+        required,
+        range(min = 0, max = 1000, message = "must be between 0 and 1000")
+    )]
+    quantity: Option<i32>,
+}
+
+async fn load_cart<C: ConnectionTrait>(conn: &C, user_id: i32) -> Result<Cart, AppError> {
+    let items: Vec<CartLine> = Entity::find()
+        .filter(Column::UserId.eq(user_id))
+        .find_also_related(product::Entity)
+        .order_by_asc(Column::Id)
+        .all(conn)
+        .await?
+        .into_iter()
+        // Cart items go with their product, so this always matches.
+        .filter_map(|(item, product)| product.map(|product| (item, product)))
+        .map(|(item, product)| CartLine {
+            product_id: product.id,
+            title: product.title,
+            unit_price: product.price,
+            quantity: item.quantity,
+            line_total: i64::from(product.price) * i64::from(item.quantity),
+            stock: product.stock,
+        })
+        .collect();
+
+    Ok(Cart {
+        total: items.iter().map(|item| item.line_total).sum(),
+        items,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/cart",
+    tag = "cart",
+    responses(
+        (status = 200, description = "The caller's cart", body = Cart),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_cart(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+) -> Result<Json<Cart>, AppError> {
+    Ok(Json(load_cart(&conn, claims.sub).await?))
+}
+
+/// Sets how many units of a product the caller's cart holds.
+#[utoipa::path(
+    put,
+    path = "/cart",
+    tag = "cart",
+    request_body = SetCartItem,
+    responses(
+        (status = 200, description = "The updated cart", body = Cart),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn put_cart(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    ValidatedJson(item): ValidatedJson<SetCartItem>,
+) -> Result<Json<Cart>, AppError> {
+    // Both fields are `required`, so the defaults are never used.
+    let product_id = item.product_id.unwrap_or_default();
+    let quantity = item.quantity.unwrap_or_default();
+
+    product::Entity::find_by_id(product_id)
+        .filter(product::Column::DeletedAt.is_null())
+        .one(&conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Product not found"))?;
+
+    if quantity == 0 {
+        Entity::delete_many()
+            .filter(Column::UserId.eq(claims.sub))
+            .filter(Column::ProductId.eq(product_id))
+            .exec(&conn)
+            .await?;
+    } else {
+        Entity::insert(ActiveModel {
+            id: ActiveValue::NotSet,
+            user_id: ActiveValue::Set(claims.sub),
+            product_id: ActiveValue::Set(product_id),
+            quantity: ActiveValue::Set(quantity),
+            added_at: ActiveValue::Set(Utc::now().naive_utc()),
+        })
+        .on_conflict(
+            OnConflict::columns([Column::UserId, Column::ProductId])
+                .update_column(Column::Quantity)
+                .to_owned(),
+        )
+        .exec(&conn)
+        .await?;
+    }
+
+    Ok(Json(load_cart(&conn, claims.sub).await?))
+}
+
+/// Empties the caller's cart.
+#[utoipa::path(
+    delete,
+    path = "/cart",
+    tag = "cart",
+    responses(
+        (status = 200, description = "The emptied cart", body = Cart),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_cart(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+) -> Result<Json<Cart>, AppError> {
+    Entity::delete_many()
+        .filter(Column::UserId.eq(claims.sub))
+        .exec(&conn)
+        .await?;
+
+    Ok(Json(load_cart(&conn, claims.sub).await?))
+}
@@ -1,85 +1,700 @@
 use std::collections::HashMap;
 
-use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    Json,
-};
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
 
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
-    ModelTrait, QueryFilter,
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait,
+    DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
 };
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
 
 use crate::{
-    entities::category::{ActiveModel, Column, Entity, Model},
-    utils::app_error::AppError,
+    entities::{
+        category::{ActiveModel, Column, Entity, Model},
+        product,
+        sea_orm_active_enums::{AuditAction, AuditEntity, Role},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        audit::{self, snapshot, Actor, Change},
+        extract::{Json, Query, ValidatedJson},
+        jwt::Claims,
+        pagination::{paginate, Page, PageParams},
+        soft_delete::include_deleted,
+        validation::{slugify, validate_slug},
+    },
 };
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+const SORTABLE: &[(&str, Column)] = &[
+    ("id", Column::Id),
+    ("name", Column::Name),
+    ("slug", Column::Slug),
+];
+
+fn invalid(field: &'static str, code: &'static str, message: &'static str) -> AppError {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        field,
+        ValidationError::new(code).with_message(message.into()),
+    );
+    errors.into()
+}
+
+async fn audit_change<C: ConnectionTrait>(
+    conn: &C,
+    actor: &Actor,
+    action: AuditAction,
+    before: Option<&Model>,
+    after: &Model,
+) -> Result<(), AppError> {
+    audit::record(
+        conn,
+        actor,
+        Change {
+            action,
+            entity: AuditEntity::Category,
+            entity_id: after.id,
+            before: before.map(snapshot),
+            after: Some(snapshot(after)),
+        },
+    )
+    .await
+}
+
+/// Looks up the category a request refers to by slug, reporting an unknown
+/// slug as a validation error on `field`. Deleted categories don't count.
+pub(crate) async fn category_by_slug<C: ConnectionTrait>(
+    conn: &C,
+    field: &'static str,
+    slug: &str,
+) -> Result<Model, AppError> {
+    Entity::find()
+        .filter(Column::Slug.eq(slug))
+        .filter(Column::DeletedAt.is_null())
+        .one(conn)
+        .await?
+        .ok_or_else(|| invalid(field, "unknown_category", "no category has this slug"))
+}
+
+/// The given categories and every live category nested below them.
+pub(crate) async fn with_descendants<C: ConnectionTrait>(
+    conn: &C,
+    ids: Vec<i32>,
+) -> Result<Vec<i32>, AppError> {
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    for category in Entity::find()
+        .filter(Column::DeletedAt.is_null())
+        .all(conn)
+        .await?
+    {
+        if let Some(parent_id) = category.parent_id {
+            children.entry(parent_id).or_default().push(category.id);
+        }
+    }
+
+    let mut found = ids.clone();
+    let mut pending = ids;
+    while let Some(id) = pending.pop() {
+        for &child in children.get(&id).into_iter().flatten() {
+            found.push(child);
+            pending.push(child);
+        }
+    }
+
+    Ok(found)
+}
+
+/// Names only need to be unique among live siblings; slugs are unique
+/// globally, deleted categories included, so a restore never clashes.
+async fn ensure_unique_name<C: ConnectionTrait>(
+    conn: &C,
+    name: &str,
+    parent_id: Option<i32>,
+    except: Option<i32>,
+) -> Result<(), AppError> {
+    let mut condition = Condition::all()
+        .add(Column::Name.eq(name))
+        .add(Column::DeletedAt.is_null())
+        .add(match parent_id {
+            Some(parent_id) => Column::ParentId.eq(parent_id),
+            None => Column::ParentId.is_null(),
+        });
+
+    if let Some(id) = except {
+        condition = condition.add(Column::Id.ne(id));
+    }
 
+    if Entity::find().filter(condition).count(conn).await? > 0 {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            format!("A category named {} already exists here", name),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn ensure_parent_exists<C: ConnectionTrait>(
+    conn: &C,
+    parent_id: i32,
+) -> Result<(), AppError> {
+    match Entity::find_by_id(parent_id)
+        .filter(Column::DeletedAt.is_null())
+        .one(conn)
+        .await?
+    {
+        Some(_) => Ok(()),
+        None => Err(invalid(
+            "parent_id",
+            "unknown_category",
+            "no category has this id",
+        )),
+    }
+}
+
+/// Filters accepted by `GET /category`; all given filters must match.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategoryFilter {
+    /// Substring of the category name.
+    name: Option<String>,
+    slug: Option<String>,
+    /// Only direct subcategories of this category.
+    parent_id: Option<i32>,
+    /// Also list deleted categories; requires the editor role.
+    include_deleted: Option<bool>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/category",
+    tag = "category",
+    params(CategoryFilter, PageParams),
+    responses(
+        (status = 200, description = "Page of categories", body = Page<Model>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Deleted categories requested without the editor role", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_category(
     State(conn): State<DatabaseConnection>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<Vec<Model>>, AppError> {
+    claims: Claims,
+    Query(params): Query<CategoryFilter>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<Model>>, AppError> {
     let mut condition = Condition::all();
 
-    if let Some(name) = params.get("name") {
+    if let Some(name) = params.name {
         condition = condition.add(Column::Name.contains(name));
     }
 
-    match Entity::find().filter(condition).all(&conn).await {
-        Ok(categories) => Ok(Json(categories)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    if let Some(slug) = params.slug {
+        condition = condition.add(Column::Slug.eq(slug));
     }
+
+    if let Some(parent_id) = params.parent_id {
+        condition = condition.add(Column::ParentId.eq(parent_id));
+    }
+
+    if !include_deleted(&claims, params.include_deleted, Role::Editor)? {
+        condition = condition.add(Column::DeletedAt.is_null());
+    }
+
+    let categories = paginate(
+        &conn,
+        Entity::find().filter(condition),
+        &page,
+        SORTABLE,
+        "name",
+    )
+    .await?;
+
+    Ok(Json(categories))
+}
+
+/// A category with its subcategories, as returned by `GET /category/tree`.
+#[derive(Serialize, ToSchema)]
+pub struct CategoryNode {
+    id: i32,
+    name: String,
+    slug: String,
+    /// Products filed directly under this category.
+    product_count: i64,
+    /// Products in this category and all of its subcategories.
+    total_product_count: i64,
+    #[schema(no_recursion)]
+    children: Vec<CategoryNode>,
+}
+
+fn build_node(
+    category: Model,
+    children: &mut HashMap<Option<i32>, Vec<Model>>,
+    counts: &HashMap<i32, i64>,
+) -> CategoryNode {
+    let children: Vec<CategoryNode> = children
+        .remove(&Some(category.id))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| build_node(child, children, counts))
+        .collect();
+    let product_count = counts.get(&category.id).copied().unwrap_or_default();
+
+    CategoryNode {
+        id: category.id,
+        name: category.name,
+        slug: category.slug,
+        product_count,
+        total_product_count: product_count
+            + children
+                .iter()
+                .map(|child| child.total_product_count)
+                .sum::<i64>(),
+        children,
+    }
+}
+
+/// Every category nested under its parent, siblings sorted by name.
+#[utoipa::path(
+    get,
+    path = "/category/tree",
+    tag = "category",
+    responses(
+        (status = 200, description = "Top-level categories with their subcategories", body = Vec<CategoryNode>),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_category_tree(
+    State(conn): State<DatabaseConnection>,
+) -> Result<Json<Vec<CategoryNode>>, AppError> {
+    let counts: HashMap<i32, i64> = product::Entity::find()
+        .select_only()
+        .column(product::Column::CategoryId)
+        .column_as(product::Column::Id.count(), "count")
+        .filter(product::Column::DeletedAt.is_null())
+        .group_by(product::Column::CategoryId)
+        .into_tuple::<(i32, i64)>()
+        .all(&conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut children: HashMap<Option<i32>, Vec<Model>> = HashMap::new();
+    for category in Entity::find()
+        .filter(Column::DeletedAt.is_null())
+        .order_by_asc(Column::Name)
+        .order_by_asc(Column::Id)
+        .all(&conn)
+        .await?
+    {
+        children
+            .entry(category.parent_id)
+            .or_default()
+            .push(category);
+    }
+
+    let roots = children.remove(&None).unwrap_or_default();
+    Ok(Json(
+        roots
+            .into_iter()
+            .map(|root| build_node(root, &mut children, &counts))
+            .collect(),
+    ))
 }
 
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewCategory {
+    #[validate(
+        required,
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters")
+    )]
+    name: Option<String>,
+    /// Derived from the name when omitted.
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_slug")
+    )]
+    slug: Option<String>,
+    /// Nests the new category under this one.
+    parent_id: Option<i32>,
+}
+
+/// Requires the editor role.
+#[utoipa::path(
+    post,
+    path = "/category",
+    tag = "category",
+    request_body = NewCategory,
+    responses(
+        (status = 200, description = "Created category", body = Model),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 409, description = "Name taken among siblings or slug taken", body = ErrorBody),
+        (status = 422, description = "Validation failed or unknown parent", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn post_category(
     State(conn): State<DatabaseConnection>,
-    Json(category): Json<Model>,
+    claims: Claims,
+    ValidatedJson(category): ValidatedJson<NewCategory>,
 ) -> Result<Json<Model>, AppError> {
-    let new_category = ActiveModel {
-        name: ActiveValue::Set(category.name),
+    // `name` is `required`, so the default is never used.
+    let name = category.name.unwrap_or_default();
+    let slug = match category.slug {
+        Some(slug) => slug,
+        None => match slugify(&name) {
+            slug if slug.is_empty() => {
+                return Err(invalid(
+                    "slug",
+                    "slug_required",
+                    "is required when the name has no letters or digits",
+                ))
+            }
+            slug => slug,
+        },
     };
 
-    match new_category.insert(&conn).await {
-        Ok(result) => Ok(Json(result)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    let txn = conn.begin().await?;
+    if let Some(parent_id) = category.parent_id {
+        ensure_parent_exists(&txn, parent_id).await?;
+    }
+    ensure_unique_name(&txn, &name, category.parent_id, None).await?;
+
+    let new_category = ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(name),
+        slug: ActiveValue::Set(slug),
+        parent_id: ActiveValue::Set(category.parent_id),
+        deleted_at: ActiveValue::Set(None),
     }
+    .insert(&txn)
+    .await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Create,
+        None,
+        &new_category,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(new_category))
 }
 
-pub async fn delete_category(
+/// Tells an explicit `null` apart from a missing field.
+fn present<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct UpdateCategory {
+    #[validate(required)]
+    id: Option<i32>,
+    #[validate(length(min = 1, max = 64, message = "must be between 1 and 64 characters"))]
+    name: Option<String>,
+    /// Kept as is when omitted, so renaming doesn't break links.
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_slug")
+    )]
+    slug: Option<String>,
+    /// Moves the category; `null` makes it top-level.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<i32>)]
+    parent_id: Option<Option<i32>>,
+}
+
+/// Renames or moves a category. Products refer to categories by id, so they
+/// follow along. Requires the editor role.
+#[utoipa::path(
+    put,
+    path = "/category",
+    tag = "category",
+    request_body = UpdateCategory,
+    responses(
+        (status = 200, description = "Updated category", body = Model),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Category not found", body = ErrorBody),
+        (status = 409, description = "Name taken among siblings or slug taken", body = ErrorBody),
+        (status = 422, description = "Validation failed, unknown parent or cycle", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn put_category(
     State(conn): State<DatabaseConnection>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<&'static str>, AppError> {
-    if params.get("name").is_none() {
-        return Err(AppError::new(StatusCode::BAD_REQUEST, "Name is required"));
+    claims: Claims,
+    ValidatedJson(update): ValidatedJson<UpdateCategory>,
+) -> Result<Json<Model>, AppError> {
+    let txn = conn.begin().await?;
+    let category = Entity::find_by_id(update.id.unwrap_or_default())
+        .filter(Column::DeletedAt.is_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Category not found"))?;
+
+    let name = update.name.unwrap_or_else(|| category.name.clone());
+    let parent_id = update.parent_id.unwrap_or(category.parent_id);
+
+    if let Some(new_parent) = parent_id.filter(|_| parent_id != category.parent_id) {
+        ensure_parent_exists(&txn, new_parent).await?;
+        if with_descendants(&txn, vec![category.id])
+            .await?
+            .contains(&new_parent)
+        {
+            return Err(invalid(
+                "parent_id",
+                "category_cycle",
+                "must not be the category itself or one of its subcategories",
+            ));
+        }
     }
+    ensure_unique_name(&txn, &name, parent_id, Some(category.id)).await?;
 
-    let category = match Entity::find()
-        .filter(Condition::any().add(Column::Name.contains(params.get("name").unwrap())))
-        .one(&conn)
-        .await
-    {
-        Ok(Some(category)) => category,
-        Ok(None) => return Err(AppError::new(StatusCode::NOT_FOUND, "Category not found")),
-        Err(_) => {
+    let updated = ActiveModel {
+        id: ActiveValue::Unchanged(category.id),
+        name: ActiveValue::Set(name),
+        slug: ActiveValue::Set(update.slug.clone().unwrap_or(category.slug.clone())),
+        parent_id: ActiveValue::Set(parent_id),
+        deleted_at: ActiveValue::Unchanged(None),
+    }
+    .update(&txn)
+    .await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Update,
+        Some(&category),
+        &updated,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(updated))
+}
+
+/// Picks the category to delete; exactly one of `id`, `slug` or `name` must
+/// be given, and names must match exactly.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteCategory {
+    id: Option<i32>,
+    slug: Option<String>,
+    name: Option<String>,
+    /// Slug of the category to move the products to. Without it, deleting a
+    /// category that still has products is rejected.
+    reassign_to: Option<String>,
+}
+
+/// Soft-deletes a category; `/category/restore` brings it back. Requires the
+/// editor role.
+#[utoipa::path(
+    delete,
+    path = "/category",
+    tag = "category",
+    params(DeleteCategory),
+    responses(
+        (status = 200, description = "Category deleted", body = String),
+        (status = 400, description = "Not exactly one of id, slug or name", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Category not found", body = ErrorBody),
+        (status = 409, description = "Ambiguous name, or the category still has products or subcategories", body = ErrorBody),
+        (status = 422, description = "Unknown reassign_to category", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_category(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    Query(params): Query<DeleteCategory>,
+) -> Result<Json<&'static str>, AppError> {
+    let condition = match (params.id, params.slug, params.name) {
+        (Some(id), None, None) => Column::Id.eq(id),
+        (None, Some(slug), None) => Column::Slug.eq(slug),
+        (None, None, Some(name)) => Column::Name.eq(name),
+        _ => {
             return Err(AppError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error",
+                StatusCode::BAD_REQUEST,
+                "Exactly one of id, slug or name is required",
             ))
         }
     };
 
-    match category.delete(&conn).await {
-        Ok(_) => Ok(Json("Deleted")),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    let txn = conn.begin().await?;
+    let mut matches = Entity::find()
+        .filter(condition)
+        .filter(Column::DeletedAt.is_null())
+        .limit(2)
+        .all(&txn)
+        .await?;
+    let category = match (matches.pop(), matches.is_empty()) {
+        (Some(category), true) => category,
+        (Some(_), false) => {
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                "Several categories have this name, delete by id or slug",
+            )
+            .with_code("ambiguous_category"))
+        }
+        (None, _) => return Err(AppError::new(StatusCode::NOT_FOUND, "Category not found")),
+    };
+
+    let subcategories = Entity::find()
+        .filter(Column::ParentId.eq(category.id))
+        .filter(Column::DeletedAt.is_null())
+        .count(&txn)
+        .await?;
+    if subcategories > 0 {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "Category has subcategories, delete or move them first",
+        )
+        .with_code("category_has_children")
+        .with_details(json!({ "subcategories": subcategories })));
     }
+
+    let actor = Actor::from(&claims);
+    let in_category = product::Column::CategoryId.eq(category.id);
+    match params.reassign_to {
+        Some(slug) => {
+            let target = category_by_slug(&txn, "reassign_to", &slug).await?;
+            if target.id == category.id {
+                return Err(invalid(
+                    "reassign_to",
+                    "same_category",
+                    "must differ from the deleted category",
+                ));
+            }
+            // Deleted products move too, so none is left pointing here.
+            let moved = product::Entity::find()
+                .filter(in_category.clone())
+                .all(&txn)
+                .await?;
+            product::Entity::update_many()
+                .col_expr(product::Column::CategoryId, Expr::value(target.id))
+                .filter(in_category)
+                .exec(&txn)
+                .await?;
+            for before in moved {
+                let after = product::Model {
+                    category_id: target.id,
+                    ..before.clone()
+                };
+                audit::record(
+                    &txn,
+                    &actor,
+                    Change {
+                        action: AuditAction::Update,
+                        entity: AuditEntity::Product,
+                        entity_id: before.id,
+                        before: Some(snapshot(&before)),
+                        after: Some(snapshot(&after)),
+                    },
+                )
+                .await?;
+            }
+        }
+        None => {
+            let products = product::Entity::find()
+                .filter(in_category)
+                .filter(product::Column::DeletedAt.is_null())
+                .count(&txn)
+                .await?;
+            if products > 0 {
+                return Err(AppError::new(
+                    StatusCode::CONFLICT,
+                    "Category still has products, pass reassign_to to move them",
+                )
+                .with_code("category_in_use")
+                .with_details(json!({ "products": products })));
+            }
+        }
+    }
+
+    let mut deleted: ActiveModel = category.clone().into();
+    deleted.deleted_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+    let deleted = deleted.update(&txn).await?;
+    audit_change(&txn, &actor, AuditAction::Delete, Some(&category), &deleted).await?;
+    txn.commit().await?;
+
+    Ok(Json("Deleted"))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RestoreCategory {
+    /// Id of the deleted category.
+    id: i32,
+}
+
+/// Undoes the deletion of a category. Its parent must not be deleted, and no
+/// live sibling may have taken its name. Requires the editor role.
+#[utoipa::path(
+    post,
+    path = "/category/restore",
+    tag = "category",
+    params(RestoreCategory),
+    responses(
+        (status = 200, description = "Restored category", body = Model),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "No deleted category with this id", body = ErrorBody),
+        (status = 409, description = "Parent deleted or name taken among siblings", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn restore_category(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    Query(params): Query<RestoreCategory>,
+) -> Result<Json<Model>, AppError> {
+    let txn = conn.begin().await?;
+    let category = Entity::find_by_id(params.id)
+        .filter(Column::DeletedAt.is_not_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "No deleted category with this id"))?;
+
+    if let Some(parent_id) = category.parent_id {
+        let parent_live = Entity::find_by_id(parent_id)
+            .filter(Column::DeletedAt.is_null())
+            .count(&txn)
+            .await?
+            > 0;
+        if !parent_live {
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                "The parent category is deleted, restore it first",
+            )
+            .with_code("parent_deleted"));
+        }
+    }
+    ensure_unique_name(&txn, &category.name, category.parent_id, Some(category.id)).await?;
+
+    let mut restored: ActiveModel = category.clone().into();
+    restored.deleted_at = ActiveValue::Set(None);
+    let restored = restored.update(&txn).await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Restore,
+        Some(&category),
+        &restored,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(restored))
 }
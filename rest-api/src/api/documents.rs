@@ -0,0 +1,268 @@
+use std::{
+    io::{ErrorKind, SeekFrom},
+    path::{Component, Path},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Body,
+    extract::{Path as UrlPath, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use mime_guess::mime;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tracing::error;
+
+use crate::{
+    config::Config,
+    utils::app_error::{AppError, ErrorBody},
+};
+
+/// The document `/text` has always served.
+const TEXT_DOCUMENT: &str = "alice_in_wonderland.txt";
+
+/// Streams a document from the configured directory. Supports single byte
+/// ranges, `If-Range`, and `If-None-Match`/`If-Modified-Since` revalidation.
+#[utoipa::path(
+    get,
+    path = "/documents/{path}",
+    tag = "documents",
+    params(("path" = String, Path, description = "Path of the document below the documents directory")),
+    responses(
+        (status = 200, description = "The whole document", body = String),
+        (status = 206, description = "The requested byte range", body = String),
+        (status = 304, description = "Unchanged since the given ETag or date"),
+        (status = 404, description = "No such document", body = ErrorBody),
+        (status = 416, description = "Range outside the document", body = ErrorBody),
+    )
+)]
+pub async fn document(
+    State(config): State<Arc<Config>>,
+    UrlPath(path): UrlPath<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    serve(&config.documents.root, &path, &headers).await
+}
+
+/// Alice's Adventures in Wonderland, served like any other document.
+#[utoipa::path(
+    get,
+    path = "/text",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Alice's Adventures in Wonderland", body = String),
+        (status = 206, description = "The requested byte range", body = String),
+        (status = 304, description = "Unchanged since the given ETag or date"),
+        (status = 404, description = "The document is missing", body = ErrorBody),
+        (status = 416, description = "Range outside the document", body = ErrorBody),
+    )
+)]
+pub async fn text(
+    State(config): State<Arc<Config>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    serve(&config.documents.root, TEXT_DOCUMENT, &headers).await
+}
+
+fn not_found() -> AppError {
+    AppError::new(StatusCode::NOT_FOUND, "Document not found")
+}
+
+fn read_error(err: std::io::Error) -> AppError {
+    match err.kind() {
+        ErrorKind::NotFound => not_found(),
+        _ => {
+            error!("Reading document failed: {}", err);
+            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error reading document")
+        }
+    }
+}
+
+async fn serve(root: &Path, path: &str, headers: &HeaderMap) -> Result<Response, AppError> {
+    // Only plain names, so a request can't climb out of the root.
+    let relative = Path::new(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(not_found());
+    }
+
+    let mut file = tokio::fs::File::open(root.join(relative))
+        .await
+        .map_err(read_error)?;
+    let metadata = file.metadata().await.map_err(read_error)?;
+    if !metadata.is_file() {
+        return Err(not_found());
+    }
+
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = entity_tag(len, modified);
+    let last_modified = modified.map(httpdate::fmt_http_date);
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ETAG, header_value(&etag));
+    if let Some(last_modified) = &last_modified {
+        response_headers.insert(header::LAST_MODIFIED, header_value(last_modified));
+    }
+
+    if not_modified(headers, &etag, modified) {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    response_headers.insert(header::CONTENT_TYPE, header_value(&content_type(path)));
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+    let range = match headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(range) if if_range_holds(headers, &etag, last_modified.as_deref()) => {
+            byte_range(range, len)
+        }
+        _ => ByteRange::Full,
+    };
+
+    let (status, start, end) = match range {
+        ByteRange::Full => (StatusCode::OK, 0, len),
+        ByteRange::Partial(start, end) => {
+            response_headers.insert(
+                header::CONTENT_RANGE,
+                header_value(&format!("bytes {}-{}/{}", start, end - 1, len)),
+            );
+            (StatusCode::PARTIAL_CONTENT, start, end)
+        }
+        ByteRange::Unsatisfiable => {
+            return Err(
+                AppError::new(StatusCode::RANGE_NOT_SATISFIABLE, "Range not satisfiable")
+                    .with_code("range_not_satisfiable")
+                    .with_header(
+                        header::CONTENT_RANGE,
+                        header_value(&format!("bytes */{}", len)),
+                    ),
+            )
+        }
+    };
+
+    if start > 0 {
+        file.seek(SeekFrom::Start(start))
+            .await
+            .map_err(read_error)?;
+    }
+    response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(end - start));
+    let body = Body::from_stream(ReaderStream::new(file.take(end - start)));
+
+    Ok((status, response_headers, body).into_response())
+}
+
+/// Changes whenever the file's size or modification time does.
+fn entity_tag(len: u64, modified: Option<SystemTime>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since_epoch| since_epoch.as_nanos());
+    format!("\"{:x}-{:x}\"", len, modified)
+}
+
+fn header_value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).expect("ETags, dates and MIME types are valid header values")
+}
+
+/// Text documents are assumed to be UTF-8.
+fn content_type(path: &str) -> String {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    if mime.type_() == mime::TEXT && mime.get_param(mime::CHARSET).is_none() {
+        format!("{}; charset=utf-8", mime)
+    } else {
+        mime.to_string()
+    }
+}
+
+/// `If-None-Match` wins over `If-Modified-Since` when both are sent.
+fn not_modified(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|tags| {
+            tags.split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        });
+    }
+
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok());
+    match (since, modified) {
+        // HTTP dates only have whole seconds.
+        (Some(since), Some(modified)) => seconds(modified) <= seconds(since),
+        _ => false,
+    }
+}
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+/// A range only applies if the document still is what `If-Range` names;
+/// otherwise the whole, changed document is sent.
+fn if_range_holds(headers: &HeaderMap, etag: &str, last_modified: Option<&str>) -> bool {
+    match headers
+        .get(header::IF_RANGE)
+        .map(|value| value.to_str().unwrap_or_default())
+    {
+        None => true,
+        Some(validator) if validator.starts_with('"') => validator == etag,
+        Some(date) => last_modified == Some(date),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    /// Start and exclusive end.
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Parses a `Range` header against a document of `len` bytes. Malformed
+/// headers and multiple ranges are ignored, so the whole document is sent.
+fn byte_range(header: &str, len: u64) -> ByteRange {
+    let Some((first, last)) = header
+        .strip_prefix("bytes=")
+        .filter(|spec| !spec.contains(','))
+        .and_then(|spec| spec.trim().split_once('-'))
+    else {
+        return ByteRange::Full;
+    };
+
+    if first.is_empty() {
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(len.saturating_sub(suffix), len),
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = match last {
+        "" => len,
+        last => match last.parse::<u64>() {
+            Ok(last) if last >= start => last.saturating_add(1).min(len),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if start >= len {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(start, end)
+    }
+}
@@ -0,0 +1,48 @@
+use axum::{extract::State, http::StatusCode};
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::utils::{
+    app_error::{AppError, ErrorBody},
+    extract::Json,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthStatus {
+    #[schema(example = "ok")]
+    status: &'static str,
+}
+
+/// Liveness: the process is up and serving requests.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "Server is alive", body = HealthStatus))
+)]
+pub async fn healthz() -> Json<HealthStatus> {
+    Json(HealthStatus { status: "ok" })
+}
+
+/// Readiness: the database answers a ping.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = HealthStatus),
+        (status = 503, description = "Database unreachable", body = ErrorBody),
+    )
+)]
+pub async fn readyz(
+    State(conn): State<DatabaseConnection>,
+) -> Result<Json<HealthStatus>, AppError> {
+    conn.ping().await.map_err(|err| {
+        warn!("Readiness check failed: {:?}", err);
+        AppError::new(StatusCode::SERVICE_UNAVAILABLE, "Database unreachable")
+    })?;
+
+    Ok(Json(HealthStatus { status: "ready" }))
+}
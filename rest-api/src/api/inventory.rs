@@ -0,0 +1,279 @@
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use super::product::SORTABLE as PRODUCT_SORTABLE;
+use crate::{
+    entities::{
+        product,
+        sea_orm_active_enums::StockReason,
+        stock_movement::{ActiveModel, Column, Entity, Model},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        extract::{Json, Query, ValidatedJson},
+        jwt::Claims,
+        pagination::{paginate, Page, PageParams},
+    },
+};
+
+const SORTABLE: &[(&str, Column)] = &[("id", Column::Id), ("created_at", Column::CreatedAt)];
+
+/// Used by `GET /product/low-stock` when no threshold is given.
+const DEFAULT_LOW_STOCK_THRESHOLD: i32 = 5;
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct AdjustStock {
+    #[validate(required)]
+    product_id: Option<i32>,
+    /// Signed change: positive for a restock, negative for a sale, either
+    /// for a correction.
+    #[validate(required)]
+    quantity: Option<i32>,
+    #[validate(required)]
+    reason: Option<StockReason>,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    note: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MovementParams {
+    product_id: i32,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LowStockParams {
+    /// Products with at most this many units are listed, defaults to 5.
+    threshold: Option<i32>,
+}
+
+/// Rejects quantities whose sign contradicts the reason.
+fn check_quantity(quantity: i32, reason: StockReason) -> Result<(), ValidationErrors> {
+    let message = match reason {
+        _ if quantity == 0 => "must not be zero",
+        StockReason::Restock if quantity < 0 => "must be positive for a restock",
+        StockReason::Sale if quantity > 0 => "must be negative for a sale",
+        _ => return Ok(()),
+    };
+
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        "quantity",
+        ValidationError::new("sign").with_message(message.into()),
+    );
+    Err(errors)
+}
+
+/// Applies a stock change and writes its ledger entry, returning the entry and
+/// the product as it is afterwards. Run it inside a transaction.
+///
+/// The guarded UPDATE holds the row lock until commit, so concurrent changes
+/// to the same product apply one after another, each seeing the stock the
+/// previous one left, and none can take it below zero or past `i32::MAX`.
+pub(crate) async fn record_movement<C: ConnectionTrait>(
+    conn: &C,
+    product_id: i32,
+    quantity: i32,
+    reason: StockReason,
+    note: Option<String>,
+    user_id: Option<i32>,
+) -> Result<(Model, product::Model), AppError> {
+    let updated = product::Entity::update_many()
+        .col_expr(
+            product::Column::Stock,
+            Expr::col(product::Column::Stock).add(quantity),
+        )
+        .filter(product::Column::Id.eq(product_id))
+        // Compared without adding, as `stock + quantity` itself may overflow.
+        .filter(if quantity < 0 {
+            product::Column::Stock.gte(-i64::from(quantity))
+        } else {
+            product::Column::Stock.lte(i32::MAX - quantity)
+        })
+        .exec(conn)
+        .await?;
+
+    let product = product::Entity::find_by_id(product_id)
+        .one(conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Product not found"))?;
+
+    if updated.rows_affected == 0 && quantity > 0 {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            format!("Stock of {} cannot exceed {}", product.title, i32::MAX),
+        )
+        .with_code("stock_limit_exceeded")
+        .with_details(json!({
+            "product_id": product_id,
+            "stock": product.stock,
+            "quantity": quantity,
+        })));
+    }
+    if updated.rows_affected == 0 {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            format!("Not enough stock of {}", product.title),
+        )
+        .with_code("insufficient_stock")
+        .with_details(json!({
+            "product_id": product_id,
+            "stock": product.stock,
+            "quantity": quantity,
+        })));
+    }
+
+    let movement = ActiveModel {
+        id: ActiveValue::NotSet,
+        product_id: ActiveValue::Set(product_id),
+        quantity: ActiveValue::Set(quantity),
+        reason: ActiveValue::Set(reason),
+        note: ActiveValue::Set(note),
+        stock_after: ActiveValue::Set(product.stock),
+        user_id: ActiveValue::Set(user_id),
+        created_at: ActiveValue::Set(Utc::now().naive_utc()),
+    }
+    .insert(conn)
+    .await?;
+
+    Ok((movement, product))
+}
+
+/// Changes a product's stock and records why in the ledger. Stock never goes
+/// below zero, however many adjustments run at once. Requires the editor role.
+#[utoipa::path(
+    post,
+    path = "/product/stock",
+    tag = "inventory",
+    request_body = AdjustStock,
+    responses(
+        (status = 200, description = "Recorded stock movement", body = Model),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 409, description = "Not enough stock, or more than the stock can hold", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn adjust_stock(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    ValidatedJson(adjustment): ValidatedJson<AdjustStock>,
+) -> Result<Json<Model>, AppError> {
+    // Every field but `note` is `required`, so the defaults are never used.
+    let product_id = adjustment.product_id.unwrap_or_default();
+    let quantity = adjustment.quantity.unwrap_or_default();
+    let reason = adjustment.reason.unwrap_or(StockReason::Correction);
+    check_quantity(quantity, reason)?;
+
+    let txn = conn.begin().await?;
+    // Cancelled orders may still return stock to a deleted product, but
+    // manual adjustments only apply to live ones.
+    product::Entity::find_by_id(product_id)
+        .filter(product::Column::DeletedAt.is_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Product not found"))?;
+    let (movement, _) = record_movement(
+        &txn,
+        product_id,
+        quantity,
+        reason,
+        adjustment.note,
+        Some(claims.sub),
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(movement))
+}
+
+/// Stock ledger of one product, newest first unless `sort` is given.
+/// Requires the editor role.
+#[utoipa::path(
+    get,
+    path = "/product/stock",
+    tag = "inventory",
+    params(MovementParams, PageParams),
+    responses(
+        (status = 200, description = "Page of stock movements", body = Page<Model>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Product not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_stock_movements(
+    State(conn): State<DatabaseConnection>,
+    Query(params): Query<MovementParams>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<Model>>, AppError> {
+    product::Entity::find_by_id(params.product_id)
+        .one(&conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Product not found"))?;
+
+    let movements = paginate(
+        &conn,
+        Entity::find().filter(Column::ProductId.eq(params.product_id)),
+        &page,
+        SORTABLE,
+        "id:desc",
+    )
+    .await?;
+
+    Ok(Json(movements))
+}
+
+/// Products running low, emptiest first unless `sort` is given. Requires the
+/// editor role.
+#[utoipa::path(
+    get,
+    path = "/product/low-stock",
+    tag = "inventory",
+    params(LowStockParams, PageParams),
+    responses(
+        (status = 200, description = "Page of products", body = Page<product::Model>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_low_stock(
+    State(conn): State<DatabaseConnection>,
+    Query(params): Query<LowStockParams>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<product::Model>>, AppError> {
+    let threshold = params.threshold.unwrap_or(DEFAULT_LOW_STOCK_THRESHOLD);
+    if threshold < 0 {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "threshold must not be negative",
+        ));
+    }
+
+    let products = paginate(
+        &conn,
+        product::Entity::find()
+            .filter(product::Column::Stock.lte(threshold))
+            .filter(product::Column::DeletedAt.is_null()),
+        &page,
+        PRODUCT_SORTABLE,
+        "stock",
+    )
+    .await?;
+
+    Ok(Json(products))
+}
@@ -0,0 +1,14 @@
+use axum::extract::State;
+use metrics_exporter_prometheus::PrometheusHandle;
+
+/// Prometheus exposition of the request, database and auth metrics.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "metrics",
+    responses((status = 200, description = "Metrics in Prometheus text format", body = String))
+)]
+pub async fn metrics(State(handle): State<PrometheusHandle>) -> String {
+    handle.run_upkeep();
+    handle.render()
+}
@@ -1,5 +1,17 @@
+pub mod account;
+pub mod audit;
 pub mod auth;
+pub mod cart;
 pub mod category;
+pub mod documents;
+pub mod health;
+pub mod inventory;
+pub mod metrics;
+pub mod openapi;
+pub mod orders;
 pub mod product;
+pub mod product_bulk;
+pub mod product_image;
+pub mod state;
+pub mod two_factor;
 pub mod users;
-pub mod text;
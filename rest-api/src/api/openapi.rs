@@ -0,0 +1,125 @@
+use axum::response::Html;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use super::{
+    account, audit, auth, cart, category, documents, health, inventory, metrics, orders, product,
+    product_bulk, product_image, two_factor, users,
+};
+use crate::utils::extract::Json;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "axum-project", description = "REST API for users, products and orders"),
+    paths(
+        auth::login,
+        auth::login_totp,
+        auth::refresh,
+        auth::logout,
+        auth::jwks,
+        account::verify_email,
+        account::forgot_password,
+        account::reset_password,
+        users::post_user,
+        users::get_users,
+        users::put_user,
+        users::delete_user,
+        users::unlock_user,
+        users::restore_user,
+        account::resend_verification,
+        two_factor::start_totp,
+        two_factor::confirm_totp,
+        two_factor::regenerate_recovery_codes,
+        two_factor::disable_totp,
+        category::get_category,
+        category::get_category_tree,
+        category::post_category,
+        category::put_category,
+        category::delete_category,
+        category::restore_category,
+        product::get_product,
+        product::post_product,
+        product::put_product,
+        product::delete_product,
+        product::restore_product,
+        product_bulk::import_products,
+        product_bulk::export_products,
+        product_image::upload_product_images,
+        product_image::delete_product_image,
+        inventory::adjust_stock,
+        inventory::get_stock_movements,
+        inventory::get_low_stock,
+        cart::get_cart,
+        cart::put_cart,
+        cart::delete_cart,
+        orders::checkout,
+        orders::get_orders,
+        orders::put_order_status,
+        audit::get_audit_log,
+        documents::text,
+        documents::document,
+        health::healthz,
+        health::readyz,
+        metrics::metrics,
+    ),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Signup, login and token management"),
+        (name = "users", description = "User accounts"),
+        (name = "category", description = "Product categories"),
+        (name = "product", description = "Product catalog"),
+        (name = "inventory", description = "Stock levels and the stock ledger"),
+        (name = "cart", description = "The caller's shopping cart"),
+        (name = "orders", description = "Checkout and order history"),
+        (name = "audit", description = "History of changes to users, categories and products"),
+        (name = "documents", description = "Static documents"),
+        (name = "health", description = "Liveness and readiness probes"),
+        (name = "metrics", description = "Prometheus metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Registers the bearer scheme checked by `authenticate`.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>axum-project API docs</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+pub async fn docs() -> Html<&'static str> {
+    Html(SWAGGER_UI)
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use super::inventory::record_movement;
+use crate::{
+    entities::{
+        cart_item, order_line,
+        orders::{ActiveModel, Column, Entity, Model},
+        sea_orm_active_enums::{OrderStatus, Role, StockReason},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        extract::{Json, Query, ValidatedJson},
+        jwt::Claims,
+        pagination::{paginate, Page, PageParams},
+    },
+};
+
+const SORTABLE: &[(&str, Column)] = &[
+    ("id", Column::Id),
+    ("created_at", Column::CreatedAt),
+    ("total", Column::Total),
+    ("status", Column::Status),
+];
+
+#[derive(Serialize, ToSchema)]
+pub struct OrderWithLines {
+    #[serde(flatten)]
+    order: Model,
+    lines: Vec<order_line::Model>,
+}
+
+/// Filters accepted by `GET /orders`. Only editors and admins may list other
+/// users' orders.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrderFilter {
+    id: Option<i32>,
+    status: Option<OrderStatus>,
+    /// Defaults to the caller.
+    user_id: Option<i32>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct UpdateOrderStatus {
+    #[validate(required)]
+    id: Option<i32>,
+    #[validate(required)]
+    status: Option<OrderStatus>,
+}
+
+/// Editors and admins handle every order; users only their own.
+fn is_staff(claims: &Claims) -> bool {
+    claims.role >= Role::Editor
+}
+
+/// Lines of the given orders, in order, keyed by order id.
+async fn lines_by_order<C: ConnectionTrait>(
+    conn: &C,
+    order_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<order_line::Model>>, AppError> {
+    let mut lines: HashMap<i32, Vec<order_line::Model>> = HashMap::new();
+
+    for line in order_line::Entity::find()
+        .filter(order_line::Column::OrderId.is_in(order_ids))
+        .order_by_asc(order_line::Column::Id)
+        .all(conn)
+        .await?
+    {
+        lines.entry(line.order_id).or_default().push(line);
+    }
+
+    Ok(lines)
+}
+
+/// Turns the caller's cart into a pending order. Prices are copied into the
+/// order lines and the stock is taken in the same transaction, so either the
+/// whole order goes through or nothing changes.
+#[utoipa::path(
+    post,
+    path = "/orders",
+    tag = "orders",
+    responses(
+        (status = 200, description = "Placed order", body = OrderWithLines),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 409, description = "Not enough stock", body = ErrorBody),
+        (status = 422, description = "Cart is empty", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn checkout(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+) -> Result<Json<OrderWithLines>, AppError> {
+    let txn = conn.begin().await?;
+
+    // Locking products in id order keeps concurrent checkouts from deadlocking.
+    let items = cart_item::Entity::find()
+        .filter(cart_item::Column::UserId.eq(claims.sub))
+        .order_by_asc(cart_item::Column::ProductId)
+        .all(&txn)
+        .await?;
+    if items.is_empty() {
+        return Err(
+            AppError::new(StatusCode::UNPROCESSABLE_ENTITY, "Cart is empty")
+                .with_code("cart_empty"),
+        );
+    }
+
+    let now = Utc::now().naive_utc();
+    let order = ActiveModel {
+        id: ActiveValue::NotSet,
+        user_id: ActiveValue::Set(Some(claims.sub)),
+        status: ActiveValue::Set(OrderStatus::Pending),
+        total: ActiveValue::Set(0),
+        created_at: ActiveValue::Set(now),
+        updated_at: ActiveValue::Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    let mut lines = Vec::with_capacity(items.len());
+    for item in &items {
+        let (_, product) = record_movement(
+            &txn,
+            item.product_id,
+            -item.quantity,
+            StockReason::Sale,
+            Some(format!("order {}", order.id)),
+            Some(claims.sub),
+        )
+        .await?;
+
+        let line = order_line::ActiveModel {
+            id: ActiveValue::NotSet,
+            order_id: ActiveValue::Set(order.id),
+            product_id: ActiveValue::Set(Some(product.id)),
+            title: ActiveValue::Set(product.title),
+            unit_price: ActiveValue::Set(product.price),
+            quantity: ActiveValue::Set(item.quantity),
+            line_total: ActiveValue::Set(i64::from(product.price) * i64::from(item.quantity)),
+        }
+        .insert(&txn)
+        .await?;
+        lines.push(line);
+    }
+
+    let mut order: ActiveModel = order.into();
+    order.total = ActiveValue::Set(lines.iter().map(|line| line.line_total).sum());
+    let order = order.update(&txn).await?;
+
+    cart_item::Entity::delete_many()
+        .filter(cart_item::Column::UserId.eq(claims.sub))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+
+    Ok(Json(OrderWithLines { order, lines }))
+}
+
+/// Order history, newest first unless `sort` is given.
+#[utoipa::path(
+    get,
+    path = "/orders",
+    tag = "orders",
+    params(OrderFilter, PageParams),
+    responses(
+        (status = 200, description = "Page of orders", body = Page<OrderWithLines>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Another user's orders", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_orders(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    Query(params): Query<OrderFilter>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<OrderWithLines>>, AppError> {
+    let mut condition = Condition::all();
+
+    match params.user_id {
+        Some(user_id) if user_id != claims.sub && !is_staff(&claims) => {
+            return Err(AppError::new(
+                StatusCode::FORBIDDEN,
+                "You can only list your own orders",
+            ))
+        }
+        Some(user_id) => condition = condition.add(Column::UserId.eq(user_id)),
+        None => condition = condition.add(Column::UserId.eq(claims.sub)),
+    }
+
+    if let Some(id) = params.id {
+        condition = condition.add(Column::Id.eq(id));
+    }
+
+    if let Some(status) = params.status {
+        condition = condition.add(Column::Status.eq(status));
+    }
+
+    let orders = paginate(
+        &conn,
+        Entity::find().filter(condition),
+        &page,
+        SORTABLE,
+        "id:desc",
+    )
+    .await?;
+    let ids = orders.items.iter().map(|order| order.id).collect();
+    let mut lines = lines_by_order(&conn, ids).await?;
+
+    Ok(Json(orders.map_items(|orders| {
+        orders
+            .into_iter()
+            .map(|order| OrderWithLines {
+                lines: lines.remove(&order.id).unwrap_or_default(),
+                order,
+            })
+            .collect()
+    })))
+}
+
+/// Moves an order along `pending` → `paid` → `shipped`, or cancels it before
+/// it ships, putting its stock back. Users may only cancel their own pending
+/// orders; editors and admins may make any allowed transition.
+#[utoipa::path(
+    put,
+    path = "/orders/status",
+    tag = "orders",
+    request_body = UpdateOrderStatus,
+    responses(
+        (status = 200, description = "Updated order", body = OrderWithLines),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Transition not allowed for this user", body = ErrorBody),
+        (status = 404, description = "Order not found", body = ErrorBody),
+        (status = 409, description = "Transition not allowed from the current status", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn put_order_status(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    ValidatedJson(update): ValidatedJson<UpdateOrderStatus>,
+) -> Result<Json<OrderWithLines>, AppError> {
+    // Both fields are `required`, so the defaults are never used.
+    let id = update.id.unwrap_or_default();
+    let next = update.status.unwrap_or(OrderStatus::Pending);
+
+    let txn = conn.begin().await?;
+
+    let order = Entity::find_by_id(id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .filter(|order| is_staff(&claims) || order.user_id == Some(claims.sub))
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Order not found"))?;
+
+    let own_cancellation = order.status == OrderStatus::Pending && next == OrderStatus::Cancelled;
+    if !is_staff(&claims) && !own_cancellation {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "You can only cancel your own pending orders",
+        ));
+    }
+
+    if !order.status.can_become(next) {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            format!("Cannot move a {:?} order to {:?}", order.status, next).to_lowercase(),
+        )
+        .with_code("invalid_transition")
+        .with_details(json!({ "from": order.status, "to": next })));
+    }
+
+    let lines = lines_by_order(&txn, vec![order.id])
+        .await?
+        .remove(&order.id)
+        .unwrap_or_default();
+
+    if next == OrderStatus::Cancelled {
+        // Same lock order as checkout. Lines of since-deleted products have
+        // nothing to go back to.
+        let mut returns: Vec<(i32, i32)> = lines
+            .iter()
+            .filter_map(|line| line.product_id.map(|id| (id, line.quantity)))
+            .collect();
+        returns.sort_unstable();
+
+        for (product_id, quantity) in returns {
+            record_movement(
+                &txn,
+                product_id,
+                quantity,
+                StockReason::Restock,
+                Some(format!("order {} cancelled", order.id)),
+                Some(claims.sub),
+            )
+            .await?;
+        }
+    }
+
+    let mut order: ActiveModel = order.into();
+    order.status = ActiveValue::Set(next);
+    order.updated_at = ActiveValue::Set(Utc::now().naive_utc());
+    let order = order.update(&txn).await?;
+
+    txn.commit().await?;
+
+    Ok(Json(OrderWithLines { order, lines }))
+}
@@ -1,110 +1,394 @@
-use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    Json,
-};
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
 
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait,
-    ModelTrait, QueryFilter,
+    sea_query::{Expr, Func, SimpleExpr},
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseBackend,
+    DatabaseConnection, EntityTrait, Order, PaginatorTrait, QueryFilter, QueryOrder,
+    TransactionTrait,
 };
 
+use super::{
+    category::{category_by_slug, with_descendants},
+    product_image::{images_by_product, ProductImage},
+};
 use crate::{
-    entities::product::{ActiveModel, Column, Entity, Model},
-    utils::app_error::AppError,
+    entities::{
+        cart_item, category,
+        product::{ActiveModel, Column, Entity, Model},
+        sea_orm_active_enums::{AuditAction, AuditEntity, Role},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        audit::{self, snapshot, Actor, Change},
+        extract::{Json, Query, ValidatedJson},
+        jwt::Claims,
+        pagination::{paginate, Page, PageParams},
+        soft_delete::include_deleted,
+        storage::Storage,
+    },
 };
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
 
-#[derive(serde::Deserialize)]
+pub(crate) const SORTABLE: &[(&str, Column)] = &[
+    ("id", Column::Id),
+    ("title", Column::Title),
+    ("price", Column::Price),
+    ("category", Column::CategoryId),
+    ("stock", Column::Stock),
+];
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct NewProduct {
+    #[validate(
+        required,
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters")
+    )]
+    title: Option<String>,
+    #[validate(required, range(min = 0, message = "must not be negative"))]
+    price: Option<i32>,
+    /// Slug of the category.
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    category: Option<String>,
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct UpdateProduct {
+    #[validate(required)]
+    id: Option<i32>,
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    title: Option<String>,
+    #[validate(range(min = 0, message = "must not be negative"))]
+    price: Option<i32>,
+    /// Slug of the category.
+    #[validate(length(min = 1, message = "must not be empty"))]
+    category: Option<String>,
+}
+
+/// A product as listed by `GET /product`, with its image URLs.
+#[derive(serde::Serialize, ToSchema)]
+pub struct ProductWithImages {
+    #[serde(flatten)]
+    product: Model,
+    images: Vec<ProductImage>,
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UpsertModel {
     id: Option<i32>,
     title: Option<String>,
     price: Option<i32>,
+    /// Category slug.
     category: Option<String>,
 }
 
-pub async fn get_product(
-    State(conn): State<DatabaseConnection>,
-    Query(params): Query<UpsertModel>,
-) -> Result<Json<Vec<Model>>, AppError> {
+/// Filters accepted by `GET /product`; all given filters must match.
+///
+/// `category` takes a comma-separated list of slugs, e.g.
+/// `?category=books,games`, and also matches their subcategories; `q` runs a ranked full-text search over the title.
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProductFilter {
+    id: Option<i32>,
+    /// Case-insensitive substring of the title.
+    title: Option<String>,
+    /// Full-text search over the title, ranked by relevance unless `sort` is given.
+    q: Option<String>,
+    price: Option<i32>,
+    min_price: Option<i32>,
+    max_price: Option<i32>,
+    /// Comma-separated category slugs.
+    category: Option<String>,
+    /// Also list deleted products; requires the editor role.
+    include_deleted: Option<bool>,
+}
+
+const TITLE_TSVECTOR: &str = r#"to_tsvector('english', "product"."title")"#;
+
+fn title_search(q: &str) -> SimpleExpr {
+    Expr::cust_with_values(
+        format!("{} @@ websearch_to_tsquery('english', $1)", TITLE_TSVECTOR),
+        [q],
+    )
+}
+
+fn title_rank(q: &str) -> SimpleExpr {
+    Expr::cust_with_values(
+        format!(
+            "ts_rank({}, websearch_to_tsquery('english', $1))",
+            TITLE_TSVECTOR
+        ),
+        [q],
+    )
+}
+
+fn title_contains(title: &str) -> SimpleExpr {
+    Expr::expr(Func::lower(Expr::col(Column::Title))).like(format!("%{}%", title.to_lowercase()))
+}
+
+pub(crate) async fn audit_change<C: ConnectionTrait>(
+    conn: &C,
+    actor: &Actor,
+    action: AuditAction,
+    before: Option<&Model>,
+    after: &Model,
+) -> Result<(), AppError> {
+    audit::record(
+        conn,
+        actor,
+        Change {
+            action,
+            entity: AuditEntity::Product,
+            entity_id: after.id,
+            before: before.map(snapshot),
+            after: Some(snapshot(after)),
+        },
+    )
+    .await
+}
+
+/// Turns `params` into a `WHERE` condition, plus a relevance ranking when `q`
+/// runs a full-text search. Deleted products are left out unless an editor
+/// asks for them.
+pub(crate) async fn filter_products(
+    conn: &DatabaseConnection,
+    claims: &Claims,
+    params: &ProductFilter,
+) -> Result<(Condition, Option<SimpleExpr>), AppError> {
     let mut condition = Condition::all();
 
+    if !include_deleted(claims, params.include_deleted, Role::Editor)? {
+        condition = condition.add(Column::DeletedAt.is_null());
+    }
+
     if let Some(id) = params.id {
         condition = condition.add(Column::Id.eq(id))
     }
 
-    if let Some(title) = params.title {
-        condition = condition.add(Column::Title.contains(title));
+    if let Some(title) = &params.title {
+        condition = condition.add(title_contains(title));
     }
+
     if let Some(price) = params.price {
         condition = condition.add(Column::Price.eq(price));
     }
-    if let Some(category) = params.category {
-        condition = condition.add(Column::Category.contains(category));
+
+    if let (Some(min_price), Some(max_price)) = (params.min_price, params.max_price) {
+        if min_price > max_price {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                "min_price must not exceed max_price",
+            ));
+        }
+    }
+
+    if let Some(min_price) = params.min_price {
+        condition = condition.add(Column::Price.gte(min_price));
     }
 
-    match Entity::find().filter(condition).all(&conn).await {
-        Ok(products) => Ok(Json(products)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    if let Some(max_price) = params.max_price {
+        condition = condition.add(Column::Price.lte(max_price));
     }
+
+    if let Some(category) = &params.category {
+        let slugs: Vec<&str> = category
+            .split(',')
+            .map(str::trim)
+            .filter(|slug| !slug.is_empty())
+            .collect();
+        let ids = category::Entity::find()
+            .filter(category::Column::Slug.is_in(slugs))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|category| category.id)
+            .collect();
+        condition = condition.add(Column::CategoryId.is_in(with_descendants(conn, ids).await?));
+    }
+
+    let mut rank = None;
+
+    if let Some(q) = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        if conn.get_database_backend() == DatabaseBackend::Postgres {
+            condition = condition.add(title_search(q));
+            rank = Some(title_rank(q));
+        } else {
+            condition = condition.add(title_contains(q));
+        }
+    }
+
+    Ok((condition, rank))
 }
 
+#[utoipa::path(
+    get,
+    path = "/product",
+    tag = "product",
+    params(ProductFilter, PageParams),
+    responses(
+        (status = 200, description = "Page of products", body = Page<ProductWithImages>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Deleted products requested without the editor role", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_product(
+    State(conn): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn Storage>>,
+    claims: Claims,
+    Query(params): Query<ProductFilter>,
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<ProductWithImages>>, AppError> {
+    let (condition, rank) = filter_products(&conn, &claims, &params).await?;
+    let mut select = Entity::find();
+
+    if let Some(rank) = rank.filter(|_| !page.has_sort()) {
+        select = select.order_by(rank, Order::Desc);
+    }
+
+    let products = paginate(&conn, select.filter(condition), &page, SORTABLE, "id").await?;
+    let ids = products.items.iter().map(|product| product.id).collect();
+    let mut images = images_by_product(&conn, storage.as_ref(), ids).await?;
+
+    Ok(Json(products.map_items(|items| {
+        items
+            .into_iter()
+            .map(|product| ProductWithImages {
+                images: images.remove(&product.id).unwrap_or_default(),
+                product,
+            })
+            .collect()
+    })))
+}
+
+/// Requires the editor role.
+#[utoipa::path(
+    post,
+    path = "/product",
+    tag = "product",
+    request_body = NewProduct,
+    responses(
+        (status = 200, description = "Created product", body = Model),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 422, description = "Validation failed or unknown category", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn post_product(
     State(conn): State<DatabaseConnection>,
-    Json(product): Json<UpsertModel>,
+    claims: Claims,
+    ValidatedJson(product): ValidatedJson<NewProduct>,
 ) -> Result<Json<Model>, AppError> {
+    let txn = conn.begin().await?;
+    // Every field is `required`, so the defaults are never used.
+    let category =
+        category_by_slug(&txn, "category", &product.category.unwrap_or_default()).await?;
     let new_product = ActiveModel {
         id: ActiveValue::NotSet,
-        title: ActiveValue::Set(product.title.unwrap()),
-        price: ActiveValue::Set(product.price.unwrap()),
-        category: ActiveValue::Set(product.category.unwrap()),
-    };
-
-    match new_product.insert(&conn).await {
-        Ok(inserted_product) => Ok(Json(inserted_product)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+        title: ActiveValue::Set(product.title.unwrap_or_default()),
+        price: ActiveValue::Set(product.price.unwrap_or_default()),
+        category_id: ActiveValue::Set(category.id),
+        // Stock only changes through `/product/stock` so the ledger stays complete.
+        stock: ActiveValue::NotSet,
+        deleted_at: ActiveValue::Set(None),
     }
+    .insert(&txn)
+    .await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Create,
+        None,
+        &new_product,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(new_product))
 }
 
+/// Requires the editor role.
+#[utoipa::path(
+    put,
+    path = "/product",
+    tag = "product",
+    request_body = UpdateProduct,
+    responses(
+        (status = 200, description = "Updated product", body = Model),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 422, description = "Validation failed or unknown category", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn put_product(
     State(conn): State<DatabaseConnection>,
-    Json(product): Json<UpsertModel>,
+    claims: Claims,
+    ValidatedJson(product): ValidatedJson<UpdateProduct>,
 ) -> Result<Json<Model>, AppError> {
-    let result = match Entity::find_by_id(product.id.unwrap()).one(&conn).await {
-        Ok(result) => result.ok_or(AppError::new(StatusCode::NOT_FOUND, "Product not found"))?,
-        Err(_) => {
-            return Err(AppError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error",
-            ))
-        }
+    let txn = conn.begin().await?;
+    let result = Entity::find_by_id(product.id.unwrap_or_default())
+        .filter(Column::DeletedAt.is_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Product not found"))?;
+    let category_id = match product.category {
+        Some(slug) => category_by_slug(&txn, "category", &slug).await?.id,
+        None => result.category_id,
     };
 
     let new_product = ActiveModel {
-        id: ActiveValue::Set(result.id),
-        title: ActiveValue::Set(product.title.unwrap_or(result.title)),
+        id: ActiveValue::Unchanged(result.id),
+        title: ActiveValue::Set(product.title.unwrap_or(result.title.clone())),
         price: ActiveValue::Set(product.price.unwrap_or(result.price)),
-        category: ActiveValue::Set(product.category.unwrap_or(result.category)),
-    };
-
-    match new_product.update(&conn).await {
-        Ok(updated_product) => Ok(Json(updated_product)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+        category_id: ActiveValue::Set(category_id),
+        stock: ActiveValue::NotSet,
+        deleted_at: ActiveValue::NotSet,
     }
+    .update(&txn)
+    .await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Update,
+        Some(&result),
+        &new_product,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(new_product))
 }
 
+/// Soft-deletes the first live product matching any of the given fields and
+/// takes it out of every cart. Its images and order lines are kept, so
+/// `/product/restore` brings it back as it was. Requires the editor role.
+#[utoipa::path(
+    delete,
+    path = "/product",
+    tag = "product",
+    params(UpsertModel),
+    responses(
+        (status = 200, description = "Product deleted", body = String),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Product not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_product(
     State(conn): State<DatabaseConnection>,
+    claims: Claims,
     Query(params): Query<UpsertModel>,
 ) -> Result<Json<&'static str>, AppError> {
+    let txn = conn.begin().await?;
     let mut condition = Condition::any();
 
     if let Some(id) = params.id {
@@ -119,25 +403,104 @@ pub async fn delete_product(
         condition = condition.add(Column::Price.eq(price));
     }
 
-    if let Some(category) = params.category {
-        condition = condition.add(Column::Category.contains(category));
+    if let Some(slug) = params.category {
+        if let Some(category) = category::Entity::find()
+            .filter(category::Column::Slug.eq(slug))
+            .one(&txn)
+            .await?
+        {
+            condition = condition.add(Column::CategoryId.eq(category.id));
+        }
     }
 
-    let product = match Entity::find().filter(condition).one(&conn).await {
-        Ok(product) => product.ok_or(AppError::new(StatusCode::NOT_FOUND, "Product not found"))?,
-        Err(_) => {
-            return Err(AppError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error",
-            ))
-        }
-    };
+    let product = Entity::find()
+        .filter(condition)
+        .filter(Column::DeletedAt.is_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Product not found"))?;
+
+    cart_item::Entity::delete_many()
+        .filter(cart_item::Column::ProductId.eq(product.id))
+        .exec(&txn)
+        .await?;
 
-    match product.delete(&conn).await {
-        Ok(_) => Ok(Json("Deleted")),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    let mut deleted: ActiveModel = product.clone().into();
+    deleted.deleted_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+    let deleted = deleted.update(&txn).await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Delete,
+        Some(&product),
+        &deleted,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json("Deleted"))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RestoreProduct {
+    /// Id of the deleted product.
+    id: i32,
+}
+
+/// Undoes the deletion of a product. Its category must not be deleted.
+/// Requires the editor role.
+#[utoipa::path(
+    post,
+    path = "/product/restore",
+    tag = "product",
+    params(RestoreProduct),
+    responses(
+        (status = 200, description = "Restored product", body = Model),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "No deleted product with this id", body = ErrorBody),
+        (status = 409, description = "Category deleted", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn restore_product(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    Query(params): Query<RestoreProduct>,
+) -> Result<Json<Model>, AppError> {
+    let txn = conn.begin().await?;
+    let product = Entity::find_by_id(params.id)
+        .filter(Column::DeletedAt.is_not_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "No deleted product with this id"))?;
+
+    let category_live = category::Entity::find_by_id(product.category_id)
+        .filter(category::Column::DeletedAt.is_null())
+        .count(&txn)
+        .await?
+        > 0;
+    if !category_live {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "The product's category is deleted, restore it first",
+        )
+        .with_code("category_deleted"));
     }
+
+    let mut restored: ActiveModel = product.clone().into();
+    restored.deleted_at = ActiveValue::Set(None);
+    let restored = restored.update(&txn).await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Restore,
+        Some(&product),
+        &restored,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(restored))
 }
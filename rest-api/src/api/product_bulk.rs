@@ -0,0 +1,663 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    body::{Body, BodyDataStream, Bytes},
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::{stream, StreamExt};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use super::product::{audit_change, filter_products, ProductFilter};
+use crate::{
+    entities::{
+        category,
+        product::{ActiveModel, Column, Entity, Model},
+        sea_orm_active_enums::AuditAction,
+    },
+    utils::{
+        app_error::{field_errors, AppError, ErrorBody},
+        audit::Actor,
+        extract::{Json, Query},
+        jwt::Claims,
+    },
+};
+
+/// Most rows a single import may contain.
+const MAX_ROWS: u64 = 10_000;
+/// Longest accepted row, in bytes.
+const MAX_ROW_BYTES: usize = 64 * 1024;
+/// Row errors listed in a report; any further ones are only counted.
+const MAX_REPORTED_ERRORS: usize = 100;
+/// Products fetched per query while exporting.
+const EXPORT_BATCH: u64 = 500;
+/// Columns of exported CSV files, in order.
+const CSV_COLUMNS: [&str; 5] = ["id", "title", "price", "category", "stock"];
+
+const CSV_CONTENT_TYPE: &str = "text/csv";
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+#[derive(Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    /// The format of a request body, judged by its `Content-Type`.
+    fn of_request(headers: &HeaderMap) -> Result<Self, AppError> {
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase());
+
+        match content_type.as_deref() {
+            Some(CSV_CONTENT_TYPE) => Ok(Format::Csv),
+            Some(NDJSON_CONTENT_TYPE | "application/ndjson" | "application/jsonl") => {
+                Ok(Format::Ndjson)
+            }
+            _ => Err(AppError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!(
+                    "Send products as {} or {}",
+                    CSV_CONTENT_TYPE, NDJSON_CONTENT_TYPE
+                ),
+            )),
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Ndjson => NDJSON_CONTENT_TYPE,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Splits a streamed body into lines without buffering more than one row.
+struct Lines {
+    stream: BodyDataStream,
+    buffer: Vec<u8>,
+    /// How far `buffer` has been searched for a newline.
+    scanned: usize,
+}
+
+fn row_too_long() -> AppError {
+    AppError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("Rows may be at most {} bytes", MAX_ROW_BYTES),
+    )
+    .with_code("row_too_long")
+}
+
+impl Lines {
+    fn new(body: Body) -> Self {
+        Self {
+            stream: body.into_data_stream(),
+            buffer: Vec::new(),
+            scanned: 0,
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<String>, AppError> {
+        loop {
+            if let Some(offset) = self.buffer[self.scanned..].iter().position(|&b| b == b'\n') {
+                let mut line: Vec<u8> = self.buffer.drain(..=self.scanned + offset).collect();
+                self.scanned = 0;
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return utf8(line).map(Some);
+            }
+            self.scanned = self.buffer.len();
+            if self.buffer.len() > MAX_ROW_BYTES {
+                return Err(row_too_long());
+            }
+
+            match self.stream.next().await {
+                Some(chunk) => {
+                    let chunk = chunk.map_err(|err| {
+                        AppError::new(StatusCode::BAD_REQUEST, "Failed to read request body")
+                            .with_details(json!(err.to_string()))
+                    })?;
+                    self.buffer.extend_from_slice(&chunk);
+                }
+                None if self.buffer.is_empty() => return Ok(None),
+                None => {
+                    self.scanned = 0;
+                    return utf8(std::mem::take(&mut self.buffer)).map(Some);
+                }
+            }
+        }
+    }
+}
+
+fn utf8(line: Vec<u8>) -> Result<String, AppError> {
+    String::from_utf8(line).map_err(|_| {
+        AppError::new(StatusCode::BAD_REQUEST, "Body must be UTF-8").with_code("invalid_encoding")
+    })
+}
+
+/// Yields the non-blank rows of a body with the line each one starts on.
+struct Rows {
+    lines: Lines,
+    format: Format,
+    line: u64,
+}
+
+impl Rows {
+    async fn next(&mut self) -> Result<Option<(u64, String)>, AppError> {
+        loop {
+            let Some(mut row) = self.lines.next().await? else {
+                return Ok(None);
+            };
+            self.line += 1;
+            let start = self.line;
+
+            // A quoted CSV field may span lines; quotes inside one are
+            // doubled, so an odd count means the row continues.
+            if let Format::Csv = self.format {
+                while row.matches('"').count() % 2 == 1 {
+                    let Some(next) = self.lines.next().await? else {
+                        break;
+                    };
+                    self.line += 1;
+                    row.push('\n');
+                    row.push_str(&next);
+                    if row.len() > MAX_ROW_BYTES {
+                        return Err(row_too_long());
+                    }
+                }
+            }
+
+            if !row.trim().is_empty() {
+                return Ok(Some((start, row)));
+            }
+        }
+    }
+}
+
+fn parse_csv(row: &str) -> Result<csv::StringRecord, String> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(row.as_bytes())
+        .records()
+        .next()
+        .unwrap_or_else(|| Ok(csv::StringRecord::new()))
+        .map_err(|err| err.to_string())
+}
+
+/// Describes a row that doesn't fit `ImportRow` by column name; the
+/// positions `csv` reports are relative to the lone row it was given.
+fn csv_error(err: csv::Error, headers: Option<&csv::StringRecord>) -> String {
+    match err.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => {
+            let column = err
+                .field()
+                .and_then(|field| headers?.get(field as usize))
+                .unwrap_or("row");
+            format!("{}: {}", column, err.kind())
+        }
+        _ => err.to_string(),
+    }
+}
+
+/// One product of an import. Rows with an `id` update that product, the
+/// rest are created. Stock is left alone, so exports can be re-imported.
+#[derive(Deserialize, Validate)]
+struct ImportRow {
+    id: Option<i32>,
+    #[validate(
+        required,
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters")
+    )]
+    title: Option<String>,
+    #[validate(required, range(min = 0, message = "must not be negative"))]
+    price: Option<i32>,
+    /// Slug of the category.
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    category: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RowError {
+    /// Line the row starts on, counting the CSV header.
+    line: u64,
+    message: String,
+    /// Per-field messages, as in other validation errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    fields: Option<Value>,
+}
+
+impl RowError {
+    fn invalid(line: u64, errors: &ValidationErrors) -> Self {
+        Self {
+            line,
+            message: "Validation failed".to_owned(),
+            fields: Some(field_errors(errors)),
+        }
+    }
+}
+
+fn unknown(field: &'static str, code: &'static str, message: &'static str) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        field,
+        ValidationError::new(code).with_message(message.into()),
+    );
+    errors
+}
+
+/// Outcome of an import. In a dry run, `created` and `updated` count what
+/// would have happened.
+#[derive(Serialize, ToSchema)]
+pub struct ImportReport {
+    dry_run: bool,
+    rows: u64,
+    created: u64,
+    updated: u64,
+    failed: u64,
+    /// The first 100 failed rows.
+    errors: Vec<RowError>,
+}
+
+impl ImportReport {
+    fn fail(&mut self, error: RowError) {
+        self.failed += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(error);
+        }
+    }
+}
+
+struct ValidRow {
+    line: u64,
+    id: Option<i32>,
+    title: String,
+    price: i32,
+    category_id: i32,
+}
+
+/// Parses and validates every row as it streams in. Only rows that passed
+/// are returned; the rest are recorded in `report`.
+async fn read_rows(
+    body: Body,
+    format: Format,
+    categories: &HashMap<String, i32>,
+    report: &mut ImportReport,
+) -> Result<Vec<ValidRow>, AppError> {
+    let mut rows = Rows {
+        lines: Lines::new(body),
+        format,
+        line: 0,
+    };
+    let mut valid = Vec::new();
+
+    let headers = match format {
+        Format::Csv => match rows.next().await? {
+            Some((_, header)) => Some(parse_csv(&header).map_err(|err| {
+                AppError::new(StatusCode::BAD_REQUEST, "Malformed CSV header")
+                    .with_details(json!(err))
+            })?),
+            None => None,
+        },
+        Format::Ndjson => None,
+    }
+    .map(|header| header.iter().map(str::trim).collect::<csv::StringRecord>());
+
+    while let Some((line, row)) = rows.next().await? {
+        report.rows += 1;
+        if report.rows > MAX_ROWS {
+            return Err(AppError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("At most {} rows may be imported at once", MAX_ROWS),
+            )
+            .with_code("too_many_rows"));
+        }
+
+        let parsed: Result<ImportRow, String> = match format {
+            Format::Csv => parse_csv(&row).and_then(|record| {
+                record
+                    .deserialize(headers.as_ref())
+                    .map_err(|err| csv_error(err, headers.as_ref()))
+            }),
+            Format::Ndjson => serde_json::from_str(&row).map_err(|err| err.to_string()),
+        };
+        let row = match parsed {
+            Ok(row) => row,
+            Err(message) => {
+                report.fail(RowError {
+                    line,
+                    message,
+                    fields: None,
+                });
+                continue;
+            }
+        };
+
+        if let Err(errors) = row.validate() {
+            report.fail(RowError::invalid(line, &errors));
+            continue;
+        }
+
+        // Every field but `id` is `required`, so the defaults are never used.
+        let Some(&category_id) = categories.get(&row.category.unwrap_or_default()) else {
+            report.fail(RowError::invalid(
+                line,
+                &unknown("category", "unknown_category", "no category has this slug"),
+            ));
+            continue;
+        };
+
+        valid.push(ValidRow {
+            line,
+            id: row.id,
+            title: row.title.unwrap_or_default(),
+            price: row.price.unwrap_or_default(),
+            category_id,
+        });
+    }
+
+    Ok(valid)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportParams {
+    /// Validate and report without saving anything.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Creates or updates products from a CSV file with a header row (`id`,
+/// `title`, `price`, `category`) or from JSON Lines with the same fields.
+/// Rows with an `id` update that product; `category` takes a slug and any
+/// other columns, such as `stock`, are ignored.
+///
+/// Either every row is saved or, if any fails, none is and the failures are
+/// returned. With `dry_run=true` nothing is saved and the report lists every
+/// failure. Requires the editor role.
+#[utoipa::path(
+    post,
+    path = "/product/import",
+    tag = "product",
+    params(ImportParams),
+    request_body(content(
+        (String = "text/csv"),
+        (String = "application/x-ndjson"),
+    )),
+    responses(
+        (status = 200, description = "Products saved, or the dry-run report", body = ImportReport),
+        (status = 400, description = "Unreadable body or CSV header", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 413, description = "Too many rows or a row too long", body = ErrorBody),
+        (status = 415, description = "Neither CSV nor JSON Lines", body = ErrorBody),
+        (status = 422, description = "Some rows failed; details hold the report", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn import_products(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    Query(params): Query<ImportParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<ImportReport>, AppError> {
+    let format = Format::of_request(&headers)?;
+    let categories: HashMap<String, i32> = category::Entity::find()
+        .filter(category::Column::DeletedAt.is_null())
+        .all(&conn)
+        .await?
+        .into_iter()
+        .map(|category| (category.slug, category.id))
+        .collect();
+
+    let mut report = ImportReport {
+        dry_run: params.dry_run,
+        rows: 0,
+        created: 0,
+        updated: 0,
+        failed: 0,
+        errors: Vec::new(),
+    };
+    // Rows are parsed while the body arrives, so a slow client doesn't
+    // hold the transaction open.
+    let rows = read_rows(body, format, &categories, &mut report).await?;
+
+    let actor = Actor::from(&claims);
+    let txn = conn.begin().await?;
+    for row in rows {
+        let title = ActiveValue::Set(row.title);
+        let price = ActiveValue::Set(row.price);
+        let category_id = ActiveValue::Set(row.category_id);
+
+        match row.id {
+            Some(id) => {
+                let Some(before) = Entity::find_by_id(id)
+                    .filter(Column::DeletedAt.is_null())
+                    .one(&txn)
+                    .await?
+                else {
+                    report.fail(RowError::invalid(
+                        row.line,
+                        &unknown("id", "unknown_product", "no product has this id"),
+                    ));
+                    continue;
+                };
+                let after = ActiveModel {
+                    id: ActiveValue::Unchanged(id),
+                    title,
+                    price,
+                    category_id,
+                    stock: ActiveValue::NotSet,
+                    deleted_at: ActiveValue::NotSet,
+                }
+                .update(&txn)
+                .await?;
+                audit_change(&txn, &actor, AuditAction::Update, Some(&before), &after).await?;
+                report.updated += 1;
+            }
+            None => {
+                let created = ActiveModel {
+                    id: ActiveValue::NotSet,
+                    title,
+                    price,
+                    category_id,
+                    stock: ActiveValue::NotSet,
+                    deleted_at: ActiveValue::Set(None),
+                }
+                .insert(&txn)
+                .await?;
+                audit_change(&txn, &actor, AuditAction::Create, None, &created).await?;
+                report.created += 1;
+            }
+        }
+    }
+
+    if report.dry_run {
+        txn.rollback().await?;
+        return Ok(Json(report));
+    }
+
+    if report.failed > 0 {
+        txn.rollback().await?;
+        return Err(AppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{} rows failed, nothing was imported", report.failed),
+        )
+        .with_code("import_failed")
+        .with_details(json!({
+            "rows": report.rows,
+            "failed": report.failed,
+            "errors": report.errors,
+        })));
+    }
+
+    txn.commit().await?;
+    Ok(Json(report))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportParams {
+    /// `csv` (the default) or `ndjson`.
+    #[serde(default)]
+    #[param(inline)]
+    format: Format,
+}
+
+#[derive(Serialize)]
+struct ExportRow<'a> {
+    id: i32,
+    title: &'a str,
+    price: i32,
+    category: &'a str,
+    stock: i32,
+}
+
+fn encode(
+    products: &[Model],
+    slugs: &HashMap<i32, String>,
+    format: Format,
+) -> Result<Bytes, DbErr> {
+    let rows = products.iter().map(|product| ExportRow {
+        id: product.id,
+        title: &product.title,
+        price: product.price,
+        category: slugs
+            .get(&product.category_id)
+            .map(String::as_str)
+            .unwrap_or_default(),
+        stock: product.stock,
+    });
+
+    let mut out = Vec::new();
+    match format {
+        Format::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(&mut out);
+            for row in rows {
+                writer
+                    .serialize(row)
+                    .map_err(|err| DbErr::Custom(err.to_string()))?;
+            }
+            writer
+                .flush()
+                .map_err(|err| DbErr::Custom(err.to_string()))?;
+        }
+        Format::Ndjson => {
+            for row in rows {
+                serde_json::to_writer(&mut out, &row)
+                    .map_err(|err| DbErr::Custom(err.to_string()))?;
+                out.push(b'\n');
+            }
+        }
+    }
+
+    Ok(Bytes::from(out))
+}
+
+/// Streams every product matching the same filters as `GET /product`, in id
+/// order, as CSV or JSON Lines. The files can be fed back to
+/// `/product/import`.
+#[utoipa::path(
+    get,
+    path = "/product/export",
+    tag = "product",
+    params(ProductFilter, ExportParams),
+    responses(
+        (status = 200, description = "Matching products", content(
+            (String = "text/csv"),
+            (String = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Deleted products requested without the editor role", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn export_products(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    Query(filter): Query<ProductFilter>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, AppError> {
+    let (condition, _) = filter_products(&conn, &claims, &filter).await?;
+    let slugs: Arc<HashMap<i32, String>> = Arc::new(
+        category::Entity::find()
+            .all(&conn)
+            .await?
+            .into_iter()
+            .map(|category| (category.id, category.slug))
+            .collect(),
+    );
+    let format = params.format;
+
+    let csv_header = match format {
+        Format::Csv => Some(Bytes::from(format!("{}\n", CSV_COLUMNS.join(",")))),
+        Format::Ndjson => None,
+    };
+    // Pages by id rather than offset so each batch is a cheap index scan.
+    let batches = stream::try_unfold(Some(0), move |after| {
+        let (conn, condition, slugs) = (conn.clone(), condition.clone(), slugs.clone());
+        async move {
+            let Some(after) = after else {
+                return Ok::<_, DbErr>(None);
+            };
+            let products = Entity::find()
+                .filter(Condition::all().add(condition).add(Column::Id.gt(after)))
+                .order_by_asc(Column::Id)
+                .limit(EXPORT_BATCH)
+                .all(&conn)
+                .await?;
+
+            let next = match products.last() {
+                Some(last) if products.len() as u64 == EXPORT_BATCH => Some(last.id),
+                Some(_) => None,
+                None => return Ok(None),
+            };
+            Ok(Some((encode(&products, &slugs, format)?, next)))
+        }
+    })
+    .inspect(|batch| {
+        if let Err(err) = batch {
+            error!("Product export failed: {:?}", err);
+        }
+    });
+    let body = stream::iter(csv_header.map(Ok)).chain(batches);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"products.{}\"", format.extension()),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
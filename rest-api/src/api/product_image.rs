@@ -0,0 +1,325 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use rand::RngCore;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    config::Config,
+    entities::{
+        product::{self, Entity as Product},
+        product_image::{ActiveModel, Column, Entity, Model},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        extract::{Json, Multipart, Query},
+        images::{process_image, ProcessedImage},
+        storage::Storage,
+    },
+};
+
+/// Multipart field every uploaded image is sent in.
+const IMAGE_FIELD: &str = "image";
+
+#[derive(Serialize, ToSchema)]
+pub struct ProductImage {
+    id: i32,
+    url: String,
+    thumbnail_url: String,
+    content_type: String,
+    size_bytes: i64,
+    width: i32,
+    height: i32,
+}
+
+impl ProductImage {
+    fn new(image: Model, storage: &dyn Storage) -> Self {
+        Self {
+            id: image.id,
+            url: storage.url(&image.storage_key),
+            thumbnail_url: storage.url(&image.thumbnail_key),
+            content_type: image.content_type,
+            size_bytes: image.size_bytes,
+            width: image.width,
+            height: image.height,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UploadParams {
+    product_id: i32,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteParams {
+    id: i32,
+}
+
+/// Images of the given products, in upload order, keyed by product id.
+pub async fn images_by_product(
+    conn: &DatabaseConnection,
+    storage: &dyn Storage,
+    product_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<ProductImage>>, AppError> {
+    let mut images: HashMap<i32, Vec<ProductImage>> = HashMap::new();
+
+    for image in Entity::find()
+        .filter(Column::ProductId.is_in(product_ids))
+        .order_by_asc(Column::Id)
+        .all(conn)
+        .await?
+    {
+        images
+            .entry(image.product_id)
+            .or_default()
+            .push(ProductImage::new(image, storage));
+    }
+
+    Ok(images)
+}
+
+/// Best-effort cleanup of stored files whose rows are gone or were never written.
+pub async fn remove_files(storage: &dyn Storage, keys: &[String]) {
+    for key in keys {
+        if let Err(err) = storage.delete(key).await {
+            warn!("Failed to remove stored file {}: {:?}", key, err);
+        }
+    }
+}
+
+struct Upload {
+    bytes: Vec<u8>,
+    image: ProcessedImage,
+}
+
+/// Reads every `image` part, enforcing the count and size limits, and
+/// validates each one as it arrives.
+async fn read_uploads(mut multipart: Multipart, config: &Config) -> Result<Vec<Upload>, AppError> {
+    let limits = &config.storage;
+    let mut uploads = Vec::new();
+
+    while let Some(mut field) = multipart.0.next_field().await? {
+        if field.name() != Some(IMAGE_FIELD) {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Unexpected field, send images as `{}` parts", IMAGE_FIELD),
+            )
+            .with_code("invalid_multipart"));
+        }
+        if uploads.len() == limits.max_images_per_request {
+            return Err(AppError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "At most {} images may be uploaded at once",
+                    limits.max_images_per_request
+                ),
+            )
+            .with_code("too_many_images"));
+        }
+
+        let content_type = field.content_type().map(str::to_owned);
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.chunk().await? {
+            if bytes.len() + chunk.len() > limits.max_image_bytes {
+                return Err(AppError::new(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("Images may be at most {} bytes", limits.max_image_bytes),
+                )
+                .with_code("image_too_large"));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        let thumbnail_px = limits.thumbnail_px;
+        let (bytes, image) = tokio::task::spawn_blocking(move || {
+            let image = process_image(&bytes, content_type.as_deref(), thumbnail_px);
+            (bytes, image)
+        })
+        .await
+        .map_err(|_| AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Image processing failed"))?;
+
+        uploads.push(Upload {
+            bytes,
+            image: image?,
+        });
+    }
+
+    if uploads.is_empty() {
+        return Err(AppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("At least one `{}` part is required", IMAGE_FIELD),
+        )
+        .with_code("missing_image"));
+    }
+
+    Ok(uploads)
+}
+
+/// Writes the image and its thumbnail and returns the row to insert. Keys are
+/// appended to `written` as they are stored, so the caller can remove them if
+/// a later step fails.
+async fn store_upload(
+    storage: &dyn Storage,
+    product_id: i32,
+    upload: Upload,
+    written: &mut Vec<String>,
+) -> Result<ActiveModel, AppError> {
+    let mut name = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut name);
+    let stem = format!("products/{}/{}", product_id, hex::encode(name));
+    let image = upload.image;
+
+    let storage_key = format!("{}.{}", stem, image.extension);
+    let size_bytes = upload.bytes.len() as i64;
+    storage
+        .put(&storage_key, upload.bytes, image.content_type)
+        .await?;
+    written.push(storage_key.clone());
+
+    let thumbnail_key = format!("{}_thumb.{}", stem, image.thumbnail_extension);
+    storage
+        .put(
+            &thumbnail_key,
+            image.thumbnail,
+            image.thumbnail_content_type,
+        )
+        .await?;
+    written.push(thumbnail_key.clone());
+
+    Ok(ActiveModel {
+        id: ActiveValue::NotSet,
+        product_id: ActiveValue::Set(product_id),
+        storage_key: ActiveValue::Set(storage_key),
+        thumbnail_key: ActiveValue::Set(thumbnail_key),
+        content_type: ActiveValue::Set(image.content_type.to_owned()),
+        size_bytes: ActiveValue::Set(size_bytes),
+        width: ActiveValue::Set(image.width as i32),
+        height: ActiveValue::Set(image.height as i32),
+        created_at: ActiveValue::Set(Utc::now().naive_utc()),
+    })
+}
+
+async fn save_uploads(
+    conn: &DatabaseConnection,
+    storage: &dyn Storage,
+    product_id: i32,
+    uploads: Vec<Upload>,
+    written: &mut Vec<String>,
+) -> Result<Vec<Model>, AppError> {
+    let mut rows = Vec::with_capacity(uploads.len());
+    for upload in uploads {
+        rows.push(store_upload(storage, product_id, upload, written).await?);
+    }
+
+    let txn = conn.begin().await?;
+    let mut images = Vec::with_capacity(rows.len());
+    for row in rows {
+        images.push(row.insert(&txn).await?);
+    }
+    txn.commit().await?;
+
+    Ok(images)
+}
+
+/// Attaches images to a product. Accepts JPEG, PNG and WebP; a thumbnail is
+/// generated for each. Either every image is saved or none is. Requires the
+/// editor role.
+#[utoipa::path(
+    post,
+    path = "/product/images",
+    tag = "product",
+    params(UploadParams),
+    request_body(
+        content_type = "multipart/form-data",
+        description = "One or more `image` parts, each a JPEG, PNG or WebP file"
+    ),
+    responses(
+        (status = 200, description = "Uploaded images", body = Vec<ProductImage>),
+        (status = 400, description = "Malformed multipart body", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Product not found", body = ErrorBody),
+        (status = 413, description = "Too many or too large images", body = ErrorBody),
+        (status = 415, description = "Not a JPEG, PNG or WebP image", body = ErrorBody),
+        (status = 422, description = "No image or undecodable image", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn upload_product_images(
+    State(conn): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn Storage>>,
+    State(config): State<Arc<Config>>,
+    Query(params): Query<UploadParams>,
+    multipart: Multipart,
+) -> Result<Json<Vec<ProductImage>>, AppError> {
+    Product::find_by_id(params.product_id)
+        .filter(product::Column::DeletedAt.is_null())
+        .one(&conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Product not found"))?;
+
+    let uploads = read_uploads(multipart, &config).await?;
+
+    let mut written = Vec::new();
+    match save_uploads(
+        &conn,
+        storage.as_ref(),
+        params.product_id,
+        uploads,
+        &mut written,
+    )
+    .await
+    {
+        Ok(images) => Ok(Json(
+            images
+                .into_iter()
+                .map(|image| ProductImage::new(image, storage.as_ref()))
+                .collect(),
+        )),
+        Err(err) => {
+            remove_files(storage.as_ref(), &written).await;
+            Err(err)
+        }
+    }
+}
+
+/// Removes an image and its thumbnail. Requires the editor role.
+#[utoipa::path(
+    delete,
+    path = "/product/images",
+    tag = "product",
+    params(DeleteParams),
+    responses(
+        (status = 200, description = "Image deleted", body = String),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Insufficient role", body = ErrorBody),
+        (status = 404, description = "Image not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_product_image(
+    State(conn): State<DatabaseConnection>,
+    State(storage): State<Arc<dyn Storage>>,
+    Query(params): Query<DeleteParams>,
+) -> Result<Json<&'static str>, AppError> {
+    let image = Entity::find_by_id(params.id)
+        .one(&conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "Image not found"))?;
+
+    let keys = [image.storage_key.clone(), image.thumbnail_key.clone()];
+    image.delete(&conn).await?;
+    remove_files(storage.as_ref(), &keys).await;
+
+    Ok(Json("Deleted"))
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::utils::hash::PasswordHasher;
+use crate::utils::lockout::LoginLockout;
+use crate::utils::mailer::Mailer;
+use crate::utils::signing_keys::SigningKeys;
+use crate::utils::storage::Storage;
+
+use axum::extract::FromRef;
+use metrics_exporter_prometheus::PrometheusHandle;
+use sea_orm::DatabaseConnection;
+
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub conn: DatabaseConnection,
+    pub config: Arc<Config>,
+    pub metrics: PrometheusHandle,
+    pub lockout: Arc<LoginLockout>,
+    pub storage: Arc<dyn Storage>,
+    pub hasher: Arc<dyn PasswordHasher>,
+    pub keys: Arc<SigningKeys>,
+    pub mailer: Arc<dyn Mailer>,
+}
@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::users::audit_change;
+use crate::{
+    config::Config,
+    entities::{
+        sea_orm_active_enums::{AuditAction, Role},
+        users::{ActiveModel, Column, Entity, Model},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        audit::Actor,
+        extract::{Json, ValidatedJson},
+        jwt::Claims,
+        two_factor::{
+            delete_recovery_codes, generate_secret, matching_step, replace_recovery_codes, totp,
+            verify_code,
+        },
+    },
+};
+
+async fn find_live<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, AppError> {
+    Entity::find_by_id(id)
+        .filter(Column::DeletedAt.is_null())
+        .one(conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found"))
+}
+
+fn invalid_code() -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, "Invalid code").with_code("invalid_code")
+}
+
+fn not_enabled() -> AppError {
+    AppError::new(
+        StatusCode::CONFLICT,
+        "Two-factor authentication is not enabled",
+    )
+    .with_code("totp_not_enabled")
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TotpEnrollment {
+    /// Base32 secret, for entering into an authenticator app by hand.
+    secret: String,
+    /// `otpauth://` URI, usually shown as a QR code.
+    otpauth_uri: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodes {
+    /// Each one replaces a code from the app once; they are shown only now.
+    recovery_codes: Vec<String>,
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct TotpCode {
+    /// A code from the authenticator app or, where noted, a recovery code.
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    code: Option<String>,
+}
+
+/// Starts enrolling the caller in two-factor authentication with a new
+/// secret. Logins don't ask for codes until the enrollment is confirmed.
+#[utoipa::path(
+    post,
+    path = "/users/totp",
+    tag = "users",
+    responses(
+        (status = 200, description = "Secret to add to an authenticator app", body = TotpEnrollment),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 409, description = "Two-factor authentication is already enabled", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn start_totp(
+    State(conn): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    claims: Claims,
+) -> Result<Json<TotpEnrollment>, AppError> {
+    let user = find_live(&conn, claims.sub).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already enabled",
+        )
+        .with_code("totp_enabled"));
+    }
+
+    let secret = generate_secret();
+    let otpauth_uri = totp(&secret, &config.auth.totp, &user.username)?.get_url();
+    let mut pending: ActiveModel = user.into();
+    pending.totp_secret = ActiveValue::Set(Some(secret.clone()));
+    pending.totp_last_step = ActiveValue::Set(None);
+    pending.update(&conn).await?;
+
+    Ok(Json(TotpEnrollment {
+        secret,
+        otpauth_uri,
+    }))
+}
+
+/// Enables two-factor authentication with a code from the app set up by
+/// `POST /users/totp`, and returns the recovery codes.
+#[utoipa::path(
+    post,
+    path = "/users/totp/confirm",
+    tag = "users",
+    request_body = TotpCode,
+    responses(
+        (status = 200, description = "Enabled; the recovery codes", body = RecoveryCodes),
+        (status = 400, description = "Wrong code", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 409, description = "No enrollment started, or already enabled", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn confirm_totp(
+    State(conn): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    claims: Claims,
+    ValidatedJson(request): ValidatedJson<TotpCode>,
+) -> Result<Json<RecoveryCodes>, AppError> {
+    let txn = conn.begin().await?;
+    let user = find_live(&txn, claims.sub).await?;
+    let secret = match (&user.totp_secret, user.totp_enabled_at) {
+        (Some(secret), None) => secret,
+        _ => {
+            return Err(
+                AppError::new(StatusCode::CONFLICT, "No two-factor enrollment is pending")
+                    .with_code("totp_not_pending"),
+            )
+        }
+    };
+
+    let step = matching_step(
+        &totp(secret, &config.auth.totp, &user.username)?,
+        request.code.as_deref().unwrap_or_default().trim(),
+        None,
+    )
+    .ok_or_else(invalid_code)?;
+
+    let mut enabled: ActiveModel = user.clone().into();
+    enabled.totp_enabled_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+    enabled.totp_last_step = ActiveValue::Set(Some(step));
+    let enabled = enabled.update(&txn).await?;
+    let recovery_codes = replace_recovery_codes(&txn, user.id).await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Update,
+        Some(&user),
+        &enabled,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+/// Replaces the caller's recovery codes; needs a code from the app or an
+/// unused recovery code.
+#[utoipa::path(
+    post,
+    path = "/users/totp/recovery-codes",
+    tag = "users",
+    request_body = TotpCode,
+    responses(
+        (status = 200, description = "The new recovery codes", body = RecoveryCodes),
+        (status = 400, description = "Wrong code", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 409, description = "Two-factor authentication is not enabled", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn regenerate_recovery_codes(
+    State(conn): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    claims: Claims,
+    ValidatedJson(request): ValidatedJson<TotpCode>,
+) -> Result<Json<RecoveryCodes>, AppError> {
+    let txn = conn.begin().await?;
+    let user = find_live(&txn, claims.sub).await?;
+    if user.totp_enabled_at.is_none() {
+        return Err(not_enabled());
+    }
+    if !verify_code(
+        &txn,
+        &user,
+        &config.auth.totp,
+        &request.code.unwrap_or_default(),
+    )
+    .await?
+    {
+        return Err(invalid_code());
+    }
+
+    let recovery_codes = replace_recovery_codes(&txn, user.id).await?;
+    txn.commit().await?;
+
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct DisableTotp {
+    /// Another user to disable it for; admin only.
+    id: Option<i32>,
+    /// A code from the app or a recovery code; required for the caller's own
+    /// account.
+    code: Option<String>,
+}
+
+/// Turns two-factor authentication off and deletes the recovery codes.
+/// Admins can do so for users who lost their device and recovery codes.
+#[utoipa::path(
+    post,
+    path = "/users/totp/disable",
+    tag = "users",
+    request_body = DisableTotp,
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = String),
+        (status = 400, description = "Wrong or missing code", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Another user's account and not an admin", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 409, description = "Two-factor authentication is not enabled", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn disable_totp(
+    State(conn): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    claims: Claims,
+    ValidatedJson(request): ValidatedJson<DisableTotp>,
+) -> Result<Json<&'static str>, AppError> {
+    let id = request.id.unwrap_or(claims.sub);
+    if id != claims.sub && claims.role != Role::Admin {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Only admins can disable two-factor authentication for other users",
+        ));
+    }
+
+    let txn = conn.begin().await?;
+    let user = find_live(&txn, id).await?;
+    if user.totp_secret.is_none() {
+        return Err(not_enabled());
+    }
+    // A stolen access token alone mustn't be enough to turn it off.
+    if id == claims.sub
+        && !verify_code(
+            &txn,
+            &user,
+            &config.auth.totp,
+            request.code.as_deref().unwrap_or_default(),
+        )
+        .await?
+    {
+        return Err(invalid_code());
+    }
+
+    let mut disabled: ActiveModel = user.clone().into();
+    disabled.totp_secret = ActiveValue::Set(None);
+    disabled.totp_enabled_at = ActiveValue::Set(None);
+    disabled.totp_last_step = ActiveValue::Set(None);
+    let disabled = disabled.update(&txn).await?;
+    delete_recovery_codes(&txn, user.id).await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Update,
+        Some(&user),
+        &disabled,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json("Two-factor authentication disabled"))
+}
@@ -1,25 +1,161 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    Json,
-};
+use axum::{extract::State, http::StatusCode};
 
+use chrono::{NaiveDateTime, Utc};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait, Order,
-    QueryFilter, QueryOrder,
+    ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, TransactionTrait,
 };
+use serde::Serialize;
+use serde_json::Value;
 
+use super::account::{normalize_email, verification_email};
 use crate::{
-    entities::users::{ActiveModel, Column, Entity, Model},
-    utils::{app_error::AppError, hash::hash_password},
+    config::Config,
+    entities::{
+        sea_orm_active_enums::{AuditAction, AuditEntity, Role},
+        users::{ActiveModel, Column, Entity, Model},
+    },
+    utils::{
+        app_error::{AppError, ErrorBody},
+        audit::{self, snapshot, Actor, Change},
+        extract::{Json, Query, ValidatedJson},
+        hash::{hash_password, PasswordHasher},
+        jwt::Claims,
+        lockout::LoginLockout,
+        mailer::{send_in_background, Mailer},
+        pagination::{paginate, Page, PageParams},
+        session::revoke_user_sessions,
+        soft_delete::include_deleted,
+        validation::{validate_password, validate_username},
+    },
 };
+use tracing::info;
+use utoipa::ToSchema;
+use validator::Validate;
+
+const SORTABLE: &[(&str, Column)] = &[
+    ("id", Column::Id),
+    ("username", Column::Username),
+    ("role", Column::Role),
+];
+
+/// A user as recorded in the audit log, without the password hash.
+fn audited(user: &Model) -> Value {
+    let mut user = snapshot(user);
+    if let Some(fields) = user.as_object_mut() {
+        fields.remove("password");
+    }
+    user
+}
+
+pub(crate) async fn audit_change<C: ConnectionTrait>(
+    conn: &C,
+    actor: &Actor,
+    action: AuditAction,
+    before: Option<&Model>,
+    after: &Model,
+) -> Result<(), AppError> {
+    audit::record(
+        conn,
+        actor,
+        Change {
+            action,
+            entity: AuditEntity::User,
+            entity_id: after.id,
+            before: before.map(audited),
+            after: Some(audited(after)),
+        },
+    )
+    .await
+}
+
+/// A user as the API returns it, without the password hash.
+#[derive(Serialize, ToSchema)]
+pub struct UserResponse {
+    id: i32,
+    username: String,
+    role: Role,
+    deleted_at: Option<NaiveDateTime>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    private: Option<PrivateFields>,
+}
+
+/// Only shown to the user themselves and to admins.
+#[derive(Serialize, ToSchema)]
+pub struct PrivateFields {
+    email: Option<String>,
+    email_verified_at: Option<NaiveDateTime>,
+    totp_enabled_at: Option<NaiveDateTime>,
+}
+
+impl UserResponse {
+    fn new(user: Model, private: bool) -> Self {
+        Self {
+            private: private.then_some(PrivateFields {
+                email: user.email,
+                email_verified_at: user.email_verified_at,
+                totp_enabled_at: user.totp_enabled_at,
+            }),
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            deleted_at: user.deleted_at,
+        }
+    }
+
+    fn for_caller(user: Model, claims: &Claims) -> Self {
+        let private = claims.can_modify_user(user.id);
+        Self::new(user, private)
+    }
+}
+
+/// Finds a user that hasn't been deleted.
+async fn find_live<C: ConnectionTrait>(conn: &C, id: i32) -> Result<Model, AppError> {
+    Entity::find_by_id(id)
+        .filter(Column::DeletedAt.is_null())
+        .one(conn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "User not found"))
+}
+
+fn parse_id(params: &HashMap<String, String>) -> Result<i32, AppError> {
+    match params.get("id") {
+        Some(id) => id
+            .parse::<i32>()
+            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "ID must be an integer")),
+        None => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "User ID not provided",
+        )),
+    }
+}
 
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    params(
+        ("id" = Option<i32>, Query, description = "Exact user id"),
+        ("username" = Option<String>, Query, description = "Substring of the username"),
+        ("include_deleted" = Option<bool>, Query, description = "Also list deleted users; admin only"),
+        PageParams,
+    ),
+    responses(
+        (status = 200, description = "Page of users", body = Page<UserResponse>),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Deleted users requested by a non-admin", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_users(
     State(conn): State<DatabaseConnection>,
+    claims: Claims,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<Vec<Model>>, AppError> {
+    Query(page): Query<PageParams>,
+) -> Result<Json<Page<UserResponse>>, AppError> {
     let mut condition = Condition::all();
 
     if let Some(id) = params.get("id") {
@@ -38,123 +174,330 @@ pub async fn get_users(
         condition = condition.add(Column::Username.contains(username));
     }
 
-    match Entity::find()
-        .filter(condition)
-        .order_by(Column::Username, Order::Asc)
-        .all(&conn)
-        .await
-    {
-        Ok(users) => Ok(Json(users)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    let requested = match params.get("include_deleted").map(String::as_str) {
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        Some(_) => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                "include_deleted must be true or false",
+            ))
+        }
+        None => None,
+    };
+    if !include_deleted(&claims, requested, Role::Admin)? {
+        condition = condition.add(Column::DeletedAt.is_null());
     }
+
+    let users = paginate(
+        &conn,
+        Entity::find().filter(condition),
+        &page,
+        SORTABLE,
+        "username",
+    )
+    .await?
+    .map_items(|users| {
+        users
+            .into_iter()
+            .map(|user| UserResponse::for_caller(user, &claims))
+            .collect()
+    });
+
+    Ok(Json(users))
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct NewUser {
+    #[validate(
+        required,
+        length(min = 3, max = 32, message = "must be between 3 and 32 characters"),
+        custom(function = "validate_username")
+    )]
+    username: Option<String>,
+    #[validate(required, custom(function = "validate_password"))]
+    password: Option<String>,
+    /// A verification link is mailed to it.
+    #[validate(email(message = "must be a valid email address"))]
+    email: Option<String>,
 }
 
-#[derive(serde::Deserialize)]
-pub struct UpsertModel {
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct UpdateUser {
+    #[validate(required)]
     id: Option<i32>,
+    #[validate(
+        length(min = 3, max = 32, message = "must be between 3 and 32 characters"),
+        custom(function = "validate_username")
+    )]
     username: Option<String>,
+    #[validate(custom(function = "validate_password"))]
     password: Option<String>,
+    role: Option<Role>,
+    /// A new address is unverified until the link mailed to it is opened.
+    #[validate(email(message = "must be a valid email address"))]
+    email: Option<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/signup",
+    tag = "auth",
+    request_body = NewUser,
+    responses(
+        (status = 200, description = "Created user", body = UserResponse),
+        (status = 409, description = "Username or email address already taken", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    )
+)]
 pub async fn post_user(
     State(conn): State<DatabaseConnection>,
-    Json(user): Json<UpsertModel>,
-) -> Result<Json<Model>, AppError> {
-    if user.username.is_none() || user.password.is_none() {
-        return Err(AppError::new(
-            StatusCode::BAD_REQUEST,
-            "Username or password not provided",
-        ));
-    }
-
-    let hashed_password = hash_password(&user.password.unwrap())?;
+    State(config): State<Arc<Config>>,
+    State(hasher): State<Arc<dyn PasswordHasher>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    ValidatedJson(user): ValidatedJson<NewUser>,
+) -> Result<Json<UserResponse>, AppError> {
+    // Both fields are `required`, so the defaults are never used.
+    let hashed_password = hash_password(&hasher, &user.password.unwrap_or_default()).await?;
 
+    let txn = conn.begin().await?;
     let new_user = ActiveModel {
         id: ActiveValue::NotSet,
-        username: ActiveValue::Set(user.username.unwrap()),
+        username: ActiveValue::Set(user.username.unwrap_or_default()),
         password: ActiveValue::Set(hashed_password),
-    };
+        role: ActiveValue::Set(Role::User),
+        deleted_at: ActiveValue::Set(None),
+        email: ActiveValue::Set(user.email.as_deref().map(normalize_email)),
+        email_verified_at: ActiveValue::Set(None),
+        totp_secret: ActiveValue::Set(None),
+        totp_enabled_at: ActiveValue::Set(None),
+        totp_last_step: ActiveValue::Set(None),
+    }
+    .insert(&txn)
+    .await?;
+    // Signups have no caller yet; the new account is its own actor.
+    let actor = Actor::new(new_user.id, new_user.username.clone());
+    audit_change(&txn, &actor, AuditAction::Create, None, &new_user).await?;
+    let email = verification_email(&txn, &config, &new_user).await?;
+    txn.commit().await?;
 
-    match new_user.insert(&conn).await {
-        Ok(result) => Ok(Json(result)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    if let Some(email) = email {
+        send_in_background(mailer, email);
     }
+
+    Ok(Json(UserResponse::new(new_user, true)))
 }
 
+/// Updates a user; only admins may edit other users or change roles.
+#[utoipa::path(
+    put,
+    path = "/users",
+    tag = "users",
+    request_body = UpdateUser,
+    responses(
+        (status = 200, description = "Updated user", body = UserResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Not the caller's account or role change by a non-admin", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 409, description = "Username or email address already taken", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn put_user(
     State(conn): State<DatabaseConnection>,
-    Json(user): Json<UpsertModel>,
-) -> Result<Json<Model>, AppError> {
-    let id = match user.id {
-        Some(id) => id,
-        None => {
-            return Err(AppError::new(
-                StatusCode::BAD_REQUEST,
-                "User ID not provided",
-            ))
-        }
-    };
+    State(config): State<Arc<Config>>,
+    State(hasher): State<Arc<dyn PasswordHasher>>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    claims: Claims,
+    ValidatedJson(user): ValidatedJson<UpdateUser>,
+) -> Result<Json<UserResponse>, AppError> {
+    let id = user.id.unwrap_or_default();
 
-    let found_user = match Entity::find_by_id(id).one(&conn).await {
-        Ok(user) => user.ok_or(AppError::new(StatusCode::NOT_FOUND, "User not found"))?,
-        Err(_) => {
-            return Err(AppError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error",
-            ))
-        }
-    };
+    if !claims.can_modify_user(id) {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "You can only modify your own account",
+        ));
+    }
+
+    if user.role.is_some() && claims.role != Role::Admin {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Only admins can change roles",
+        ));
+    }
 
-    let mut active_user: ActiveModel = found_user.into();
+    let txn = conn.begin().await?;
+    let found_user = find_live(&txn, id).await?;
+
+    let mut active_user: ActiveModel = found_user.clone().into();
     active_user.username = user
         .username
         .map(ActiveValue::Set)
         .unwrap_or(active_user.username);
     active_user.password = match user.password {
-        Some(password) => ActiveValue::Set(hash_password(&password)?),
+        Some(password) => ActiveValue::Set(hash_password(&hasher, &password).await?),
         None => active_user.password,
     };
+    active_user.role = user.role.map(ActiveValue::Set).unwrap_or(active_user.role);
+    match user.email.as_deref().map(normalize_email) {
+        Some(email) if found_user.email.as_ref() != Some(&email) => {
+            active_user.email = ActiveValue::Set(Some(email));
+            active_user.email_verified_at = ActiveValue::Set(None);
+        }
+        _ => {}
+    }
 
-    match active_user.update(&conn).await {
-        Ok(result) => Ok(Json(result)),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    let updated = active_user.update(&txn).await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Update,
+        Some(&found_user),
+        &updated,
+    )
+    .await?;
+    let email = if updated.email != found_user.email {
+        verification_email(&txn, &config, &updated).await?
+    } else {
+        None
+    };
+    txn.commit().await?;
+
+    if let Some(email) = email {
+        send_in_background(mailer, email);
     }
+
+    Ok(Json(UserResponse::for_caller(updated, &claims)))
 }
 
+/// Soft-deletes a user and revokes their sessions; access tokens already
+/// issued stay valid until they expire. Only admins may delete other users.
+#[utoipa::path(
+    delete,
+    path = "/users",
+    tag = "users",
+    params(("id" = i32, Query, description = "Id of the user to delete")),
+    responses(
+        (status = 200, description = "User deleted", body = String),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Not the caller's account", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_user(
     State(conn): State<DatabaseConnection>,
+    claims: Claims,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<&'static str>, AppError> {
-    let id = match params.get("id") {
-        Some(id) => id,
-        None => {
-            return Err(AppError::new(
-                StatusCode::BAD_REQUEST,
-                "User ID not provided",
-            ))
-        }
-    };
+    let id = parse_id(&params)?;
 
-    match Entity::delete_by_id(
-        id.parse::<i32>()
-            .map_err(|_| AppError::new(StatusCode::BAD_REQUEST, "ID must be an integer"))?,
+    if !claims.can_modify_user(id) {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "You can only delete your own account",
+        ));
+    }
+
+    let txn = conn.begin().await?;
+    let user = find_live(&txn, id).await?;
+    let mut deleted: ActiveModel = user.clone().into();
+    deleted.deleted_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+    let deleted = deleted.update(&txn).await?;
+
+    revoke_user_sessions(&txn, id).await?;
+
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Delete,
+        Some(&user),
+        &deleted,
     )
-    .exec(&conn)
-    .await
-    {
-        Ok(_) => Ok(Json("User deleted")),
-        Err(_) => Err(AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error",
-        )),
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json("User deleted"))
+}
+
+/// Undoes the deletion of a user; admin only. The user has to log in again.
+#[utoipa::path(
+    post,
+    path = "/users/restore",
+    tag = "users",
+    params(("id" = i32, Query, description = "Id of the deleted user")),
+    responses(
+        (status = 200, description = "Restored user", body = UserResponse),
+        (status = 400, description = "Invalid query parameters", body = ErrorBody),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Caller is not an admin", body = ErrorBody),
+        (status = 404, description = "No deleted user with this id", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn restore_user(
+    State(conn): State<DatabaseConnection>,
+    claims: Claims,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<UserResponse>, AppError> {
+    let id = parse_id(&params)?;
+
+    let txn = conn.begin().await?;
+    let user = Entity::find_by_id(id)
+        .filter(Column::DeletedAt.is_not_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::NOT_FOUND, "No deleted user with this id"))?;
+
+    let mut restored: ActiveModel = user.clone().into();
+    restored.deleted_at = ActiveValue::Set(None);
+    let restored = restored.update(&txn).await?;
+    audit_change(
+        &txn,
+        &Actor::from(&claims),
+        AuditAction::Restore,
+        Some(&user),
+        &restored,
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(Json(UserResponse::for_caller(restored, &claims)))
+}
+
+#[derive(serde::Deserialize, Validate, ToSchema)]
+pub struct UnlockUser {
+    #[validate(required, length(min = 1, message = "must not be empty"))]
+    username: Option<String>,
+}
+
+/// Lifts a login lockout on a username; admin only.
+#[utoipa::path(
+    post,
+    path = "/users/unlock",
+    tag = "users",
+    request_body = UnlockUser,
+    responses(
+        (status = 200, description = "Lockout lifted", body = String),
+        (status = 401, description = "Missing or invalid access token", body = ErrorBody),
+        (status = 403, description = "Caller is not an admin", body = ErrorBody),
+        (status = 422, description = "Validation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn unlock_user(
+    State(lockout): State<Arc<LoginLockout>>,
+    claims: Claims,
+    ValidatedJson(request): ValidatedJson<UnlockUser>,
+) -> Result<Json<&'static str>, AppError> {
+    let username = request.username.unwrap_or_default();
+
+    if lockout.unlock(&username) {
+        info!("{} unlocked login for {}", claims.username, username);
     }
+
+    Ok(Json("Account unlocked"))
 }
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    handler::Handler,
+    middleware,
+    routing::{get, post, put},
+    Router,
+};
+use sea_orm::DatabaseConnection;
+use tower_http::{
+    compression::CompressionLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
+
+use crate::api::account::{forgot_password, resend_verification, reset_password, verify_email};
+use crate::api::audit::get_audit_log;
+use crate::api::auth::{jwks, login, login_totp, logout, refresh};
+use crate::api::cart::{delete_cart, get_cart, put_cart};
+use crate::api::category::{
+    delete_category, get_category, get_category_tree, post_category, put_category, restore_category,
+};
+use crate::api::documents::{document, text};
+use crate::api::health::{healthz, readyz};
+use crate::api::inventory::{adjust_stock, get_low_stock, get_stock_movements};
+use crate::api::metrics::metrics;
+use crate::api::openapi::{docs, openapi_json};
+use crate::api::orders::{checkout, get_orders, put_order_status};
+use crate::api::product::{
+    delete_product, get_product, post_product, put_product, restore_product,
+};
+use crate::api::product_bulk::{export_products, import_products};
+use crate::api::product_image::{delete_product_image, upload_product_images};
+use crate::api::state::AppState;
+use crate::api::two_factor::{confirm_totp, disable_totp, regenerate_recovery_codes, start_totp};
+use crate::api::users::{delete_user, get_users, post_user, put_user, restore_user, unlock_user};
+use crate::config::{Config, Quota};
+
+use crate::entities::sea_orm_active_enums::Role;
+
+use crate::utils::app_error::attach_request_id;
+use crate::utils::hash::hasher;
+use crate::utils::jwt::{authenticate, require_role};
+use crate::utils::lockout::LoginLockout;
+use crate::utils::mailer::mailer;
+use crate::utils::metrics::{record_db_query, recorder, track_metrics};
+use crate::utils::rate_limit::{rate_limit, InMemoryStore, RateLimitStore, RateLimiter};
+use crate::utils::signing_keys::SigningKeys;
+use crate::utils::storage::LocalStorage;
+
+/// Builds the application router on top of an open database connection.
+pub fn create_app(conn: DatabaseConnection, config: Config) -> Router {
+    create_app_with_store(conn, config, Arc::new(InMemoryStore::default()))
+}
+
+/// Like [`create_app`], keeping rate-limit buckets in `store`.
+pub fn create_app_with_store(
+    mut conn: DatabaseConnection,
+    config: Config,
+    store: Arc<dyn RateLimitStore>,
+) -> Router {
+    let request_timeout = config.server.request_timeout();
+    let upload_timeout = config.server.upload_timeout();
+    // Multipart framing needs a little room beyond the images themselves.
+    let upload_body_limit =
+        config.storage.max_image_bytes * config.storage.max_images_per_request + 64 * 1024;
+    let storage = LocalStorage::new(&config.storage);
+    let media = Router::new().nest_service(&config.storage.base_url, ServeDir::new(storage.root()));
+    let limiter = |group, quota: Quota| {
+        config
+            .rate_limit
+            .enabled
+            .then(|| RateLimiter::new(group, quota, store.clone()))
+    };
+    let (auth_limiter, api_limiter, public_limiter) = (
+        limiter("auth", config.rate_limit.auth),
+        limiter("api", config.rate_limit.api),
+        limiter("public", config.rate_limit.public),
+    );
+
+    conn.set_metric_callback(record_db_query);
+    let state = AppState {
+        conn,
+        lockout: Arc::new(LoginLockout::new(config.auth.lockout.clone())),
+        metrics: recorder(),
+        storage: Arc::new(storage),
+        hasher: hasher(&config.auth).into(),
+        keys: Arc::new(
+            SigningKeys::from_config(&config.auth)
+                .expect("signing keys are checked by Config::validate"),
+        ),
+        mailer: mailer(&config.mail)
+            .expect("mail settings are checked by Config::validate")
+            .into(),
+        config: Arc::new(config),
+    };
+    let editor = middleware::from_fn_with_state(Role::Editor, require_role);
+    let admin = middleware::from_fn_with_state(Role::Admin, require_role);
+
+    let api = Router::new()
+        .route("/users", get(get_users).put(put_user).delete(delete_user))
+        .route("/users/unlock", post(unlock_user.layer(admin.clone())))
+        .route("/users/restore", post(restore_user.layer(admin.clone())))
+        .route("/users/verification", post(resend_verification))
+        .route("/users/totp", post(start_totp))
+        .route("/users/totp/confirm", post(confirm_totp))
+        .route(
+            "/users/totp/recovery-codes",
+            post(regenerate_recovery_codes),
+        )
+        .route("/users/totp/disable", post(disable_totp))
+        .route(
+            "/category",
+            get(get_category)
+                .post(post_category.layer(editor.clone()))
+                .put(put_category.layer(editor.clone()))
+                .delete(delete_category.layer(editor.clone())),
+        )
+        .route("/category/tree", get(get_category_tree))
+        .route(
+            "/category/restore",
+            post(restore_category.layer(editor.clone())),
+        )
+        .route(
+            "/product",
+            get(get_product)
+                .post(post_product.layer(editor.clone()))
+                .put(put_product.layer(editor.clone()))
+                .delete(delete_product.layer(editor.clone())),
+        )
+        .route(
+            "/product/restore",
+            post(restore_product.layer(editor.clone())),
+        )
+        .route(
+            "/product/stock",
+            get(get_stock_movements.layer(editor.clone())).post(adjust_stock.layer(editor.clone())),
+        )
+        .route(
+            "/product/low-stock",
+            get(get_low_stock.layer(editor.clone())),
+        )
+        .route("/cart", get(get_cart).put(put_cart).delete(delete_cart))
+        .route("/orders", get(get_orders).post(checkout))
+        .route("/orders/status", put(put_order_status))
+        .route("/audit", get(get_audit_log.layer(admin)))
+        .route_layer(TimeoutLayer::new(request_timeout));
+    // Uploads and bulk transfers get a longer timeout than the rest of the API.
+    let uploads = Router::new()
+        .route(
+            "/product/images",
+            post(upload_product_images.layer(editor.clone()))
+                .delete(delete_product_image.layer(editor.clone()))
+                .layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
+        .route("/product/import", post(import_products.layer(editor)))
+        .route("/product/export", get(export_products))
+        .route_layer(TimeoutLayer::new(upload_timeout));
+    // Rate limiting runs inside `authenticate` so it can key by user.
+    let api = with_rate_limit(api.merge(uploads), api_limiter)
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate));
+
+    let auth = Router::new()
+        .route("/auth/login", post(login))
+        .route("/auth/login/totp", post(login_totp))
+        .route("/auth/refresh", post(refresh))
+        .route("/auth/logout", post(logout))
+        .route("/auth/signup", post(post_user))
+        .route("/auth/verify-email", post(verify_email))
+        .route("/auth/forgot-password", post(forgot_password))
+        .route("/auth/reset-password", post(reset_password));
+
+    let public = Router::new()
+        .route("/text", get(text))
+        .route("/documents/*path", get(document))
+        .route("/.well-known/jwks.json", get(jwks))
+        .route("/openapi.json", get(openapi_json))
+        .route("/docs", get(docs))
+        .merge(media);
+
+    let ops = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics));
+
+    Router::new()
+        .merge(api)
+        .merge(with_rate_limit(auth, auth_limiter).route_layer(TimeoutLayer::new(request_timeout)))
+        .merge(
+            with_rate_limit(public, public_limiter).route_layer(TimeoutLayer::new(request_timeout)),
+        )
+        .merge(ops.route_layer(TimeoutLayer::new(request_timeout)))
+        .route_layer(middleware::from_fn(track_metrics))
+        .with_state(state)
+        .layer(middleware::from_fn(attach_request_id))
+        .layer(TraceLayer::new_for_http())
+        .layer(CompressionLayer::new())
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+fn with_rate_limit(router: Router<AppState>, limiter: Option<RateLimiter>) -> Router<AppState> {
+    match limiter {
+        Some(limiter) => router.route_layer(middleware::from_fn_with_state(limiter, rate_limit)),
+        None => router,
+    }
+}
@@ -0,0 +1,594 @@
+use std::{env, fmt, net::SocketAddr, path::PathBuf, time::Duration};
+
+use ::config::{Environment, File};
+use serde::Deserialize;
+
+pub use rate_limiter::Quota;
+
+use crate::utils::{mailer::mailer, signing_keys::SigningKeys};
+
+/// Default location of the configuration file, overridable with `APP_CONFIG`.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Application configuration.
+///
+/// Values are layered: built-in defaults, then the TOML file, then
+/// environment variables such as `APP__SERVER__BIND_ADDRESS=0.0.0.0:8000`.
+/// `DATABASE_URL` and `SECRET_KEY` are honoured as well.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub rate_limit: RateLimitConfig,
+    pub storage: StorageConfig,
+    pub documents: DocumentsConfig,
+    pub mail: MailConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub request_timeout_ms: u64,
+    /// Replaces `request_timeout_ms` for image uploads and product imports/exports.
+    pub upload_timeout_ms: u64,
+    /// How long in-flight requests may take to finish after a shutdown signal.
+    pub shutdown_timeout_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout_secs: u64,
+    pub acquire_timeout_secs: u64,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// HS256 secret. Only needed while `jwt.keys` is empty or tokens signed
+    /// with it should still be accepted.
+    pub secret_key: String,
+    pub jwt: JwtConfig,
+    /// Algorithm new and rehashed passwords use. Hashes made with the other
+    /// one still verify and are replaced at the next login.
+    pub password_hasher: PasswordAlgorithm,
+    pub bcrypt_cost: u32,
+    pub argon2: Argon2Config,
+    pub access_token_minutes: i64,
+    pub refresh_token_days: i64,
+    /// How long the link in an email verification mail works.
+    pub email_verification_hours: i64,
+    /// How long the link in a password reset mail works.
+    pub password_reset_minutes: i64,
+    pub totp: TotpConfig,
+    pub lockout: LockoutConfig,
+}
+
+/// Asymmetric token signing. Every key verifies tokens and is published at
+/// `/.well-known/jwks.json`; the one named by `signing_kid` also signs them.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct JwtConfig {
+    pub signing_kid: String,
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub algorithm: JwtAlgorithm,
+    /// PEM file with the SubjectPublicKeyInfo (`BEGIN PUBLIC KEY`).
+    pub public_key: PathBuf,
+    /// PEM file with the private key; only the signing key needs one.
+    pub private_key: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JwtAlgorithm {
+    Rs256,
+    /// Ed25519.
+    EdDsa,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordAlgorithm {
+    #[default]
+    Argon2id,
+    Bcrypt,
+}
+
+/// Argon2id cost parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Argon2Config {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// Two-factor authentication with time-based one-time passwords.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TotpConfig {
+    /// Shown next to the account in authenticator apps.
+    pub issuer: String,
+    /// How long the challenge token from a password login may be exchanged
+    /// for tokens with a code.
+    pub challenge_minutes: i64,
+}
+
+/// Failed-login throttling, tracked separately per username and per client IP.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LockoutConfig {
+    /// Failures after which a username is locked out.
+    pub max_attempts_per_user: u32,
+    /// Failures after which a client IP is locked out.
+    pub max_attempts_per_ip: u32,
+    /// Delay after the first failure, doubled for each further one.
+    pub backoff_base_ms: u64,
+    /// How long a lockout lasts, and how long failures are remembered.
+    pub lockout_secs: u64,
+}
+
+/// Token-bucket quotas per route group, keyed by user id or client IP.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// `/auth/*`, always keyed by client IP.
+    pub auth: Quota,
+    /// Routes that require an access token.
+    pub api: Quota,
+    /// Documentation and other unauthenticated routes.
+    pub public: Quota,
+}
+
+/// Where uploaded product images are kept and how they are validated.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Directory the local storage writes to.
+    pub root: PathBuf,
+    /// URL prefix the stored files are served under.
+    pub base_url: String,
+    pub max_image_bytes: usize,
+    pub max_images_per_request: usize,
+    /// Longest edge of generated thumbnails.
+    pub thumbnail_px: u32,
+}
+
+/// Static documents served under `/documents`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DocumentsConfig {
+    /// Directory the documents are read from.
+    pub root: PathBuf,
+}
+
+/// Outgoing mail, such as email verification and password reset links.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    /// Sender, e.g. `Shop <noreply@example.com>`.
+    pub from: String,
+    /// URL of the frontend page links point to; the token is appended as
+    /// `?token=`, e.g. `https://shop.example.com` gives
+    /// `https://shop.example.com/reset-password?token=…`.
+    pub link_base_url: String,
+    /// Directory the `file` transport writes messages to.
+    pub dir: PathBuf,
+    pub smtp: SmtpConfig,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    /// Logs messages instead of sending them; for local development.
+    #[default]
+    Log,
+    /// Writes each message to an `.eml` file in `mail.dir`.
+    File,
+    Smtp,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub tls: SmtpTls,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Upgrades a plain connection with STARTTLS, usually on port 587.
+    #[default]
+    StartTls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// Unencrypted, e.g. for a relay on localhost.
+    None,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Used when `RUST_LOG` is not set.
+    pub filter: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: "127.0.0.1:8000".to_owned(),
+            request_timeout_ms: 1000,
+            upload_timeout_ms: 30_000,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 10,
+            min_connections: 1,
+            connect_timeout_secs: 5,
+            acquire_timeout_secs: 5,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            secret_key: String::new(),
+            jwt: JwtConfig::default(),
+            password_hasher: PasswordAlgorithm::default(),
+            bcrypt_cost: bcrypt::DEFAULT_COST,
+            argon2: Argon2Config::default(),
+            access_token_minutes: 15,
+            refresh_token_days: 14,
+            email_verification_hours: 48,
+            password_reset_minutes: 30,
+            totp: TotpConfig::default(),
+            lockout: LockoutConfig::default(),
+        }
+    }
+}
+
+// The OWASP recommendation for Argon2id.
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Default for TotpConfig {
+    fn default() -> Self {
+        Self {
+            issuer: "axum-project".to_owned(),
+            challenge_minutes: 5,
+        }
+    }
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts_per_user: 5,
+            max_attempts_per_ip: 20,
+            backoff_base_ms: 500,
+            lockout_secs: 900,
+        }
+    }
+}
+
+// Keeps the secret out of logs.
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("secret_key", &"<redacted>")
+            .field("jwt", &self.jwt)
+            .field("password_hasher", &self.password_hasher)
+            .field("bcrypt_cost", &self.bcrypt_cost)
+            .field("argon2", &self.argon2)
+            .field("access_token_minutes", &self.access_token_minutes)
+            .field("refresh_token_days", &self.refresh_token_days)
+            .field("email_verification_hours", &self.email_verification_hours)
+            .field("password_reset_minutes", &self.password_reset_minutes)
+            .field("totp", &self.totp)
+            .field("lockout", &self.lockout)
+            .finish()
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            auth: Quota {
+                burst: 10,
+                per_minute: 30,
+            },
+            api: Quota {
+                burst: 100,
+                per_minute: 600,
+            },
+            public: Quota {
+                burst: 30,
+                per_minute: 120,
+            },
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("uploads"),
+            base_url: "/media".to_owned(),
+            max_image_bytes: 5 * 1024 * 1024,
+            max_images_per_request: 10,
+            thumbnail_px: 256,
+        }
+    }
+}
+
+impl Default for DocumentsConfig {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("documents"),
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::default(),
+            from: "axum-project <noreply@localhost>".to_owned(),
+            link_base_url: "http://localhost:8000".to_owned(),
+            dir: PathBuf::from("mail"),
+            smtp: SmtpConfig::default(),
+        }
+    }
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 587,
+            username: String::new(),
+            password: String::new(),
+            tls: SmtpTls::default(),
+        }
+    }
+}
+
+// Keeps the password out of logs.
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("tls", &self.tls)
+            .finish()
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Pretty,
+            filter: "info".to_owned(),
+        }
+    }
+}
+
+/// Every problem found while loading the configuration.
+#[derive(Debug)]
+pub struct ConfigError(Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid configuration:")?;
+        for problem in &self.0 {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads and validates the configuration from the file and environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = env::var("APP_CONFIG").ok();
+        let file = match &path {
+            Some(path) => File::with_name(path).required(true),
+            None => File::with_name(DEFAULT_CONFIG_FILE).required(false),
+        };
+
+        let config: Config = ::config::Config::builder()
+            .add_source(file)
+            .add_source(
+                Environment::with_prefix("APP")
+                    .prefix_separator("__")
+                    .separator("__")
+                    .try_parsing(true),
+            )
+            .set_override_option("database.url", env::var("DATABASE_URL").ok())
+            .and_then(|builder| {
+                builder.set_override_option("auth.secret_key", env::var("SECRET_KEY").ok())
+            })
+            .and_then(|builder| builder.build())
+            .and_then(|config| config.try_deserialize())
+            .map_err(|err| ConfigError(vec![err.to_string()]))?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.bind_address.parse::<SocketAddr>().is_err() {
+            problems.push(format!(
+                "server.bind_address `{}` is not a valid socket address",
+                self.server.bind_address
+            ));
+        }
+        if self.server.request_timeout_ms == 0 {
+            problems.push("server.request_timeout_ms must be greater than 0".to_owned());
+        }
+        if self.server.upload_timeout_ms == 0 {
+            problems.push("server.upload_timeout_ms must be greater than 0".to_owned());
+        }
+
+        if self.database.url.is_empty() {
+            problems.push("database.url must be set (or DATABASE_URL)".to_owned());
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be greater than 0".to_owned());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            problems.push(
+                "database.min_connections must not exceed database.max_connections".to_owned(),
+            );
+        }
+
+        if self.auth.jwt.keys.is_empty() {
+            if self.auth.secret_key.is_empty() {
+                problems.push("auth.secret_key must be set (or SECRET_KEY)".to_owned());
+            }
+        } else if let Err(err) = SigningKeys::from_config(&self.auth) {
+            problems.push(format!("auth.jwt: {}", err));
+        }
+        if !(4..=31).contains(&self.auth.bcrypt_cost) {
+            problems.push("auth.bcrypt_cost must be between 4 and 31".to_owned());
+        }
+        if let Err(err) = argon2::Params::new(
+            self.auth.argon2.memory_kib,
+            self.auth.argon2.iterations,
+            self.auth.argon2.parallelism,
+            None,
+        ) {
+            problems.push(format!("auth.argon2 parameters are invalid: {}", err));
+        }
+        if self.auth.access_token_minutes <= 0 {
+            problems.push("auth.access_token_minutes must be greater than 0".to_owned());
+        }
+        if self.auth.refresh_token_days <= 0 {
+            problems.push("auth.refresh_token_days must be greater than 0".to_owned());
+        }
+        if self.auth.email_verification_hours <= 0 || self.auth.password_reset_minutes <= 0 {
+            problems.push(
+                "auth.email_verification_hours and password_reset_minutes must be greater than 0"
+                    .to_owned(),
+            );
+        }
+        if self.auth.totp.issuer.is_empty() || self.auth.totp.issuer.contains(':') {
+            problems.push("auth.totp.issuer must be set and must not contain ':'".to_owned());
+        }
+        if self.auth.totp.challenge_minutes <= 0 {
+            problems.push("auth.totp.challenge_minutes must be greater than 0".to_owned());
+        }
+        if self.auth.lockout.max_attempts_per_user == 0
+            || self.auth.lockout.max_attempts_per_ip == 0
+        {
+            problems.push("auth.lockout.max_attempts_* must be greater than 0".to_owned());
+        }
+        if self.auth.lockout.lockout_secs == 0 {
+            problems.push("auth.lockout.lockout_secs must be greater than 0".to_owned());
+        }
+
+        for (group, quota) in [
+            ("auth", self.rate_limit.auth),
+            ("api", self.rate_limit.api),
+            ("public", self.rate_limit.public),
+        ] {
+            if quota.burst == 0 || quota.per_minute == 0 {
+                problems.push(format!(
+                    "rate_limit.{}.burst and per_minute must be greater than 0",
+                    group
+                ));
+            }
+        }
+
+        if !self.storage.base_url.starts_with('/') || self.storage.base_url.len() < 2 {
+            problems.push("storage.base_url must be a path such as /media".to_owned());
+        }
+        if self.storage.max_image_bytes == 0 || self.storage.max_images_per_request == 0 {
+            problems.push(
+                "storage.max_image_bytes and max_images_per_request must be greater than 0"
+                    .to_owned(),
+            );
+        }
+        if self.storage.thumbnail_px == 0 {
+            problems.push("storage.thumbnail_px must be greater than 0".to_owned());
+        }
+
+        if let Err(err) = mailer(&self.mail) {
+            problems.push(format!("mail: {}", err));
+        }
+        if !self.mail.link_base_url.starts_with("http://")
+            && !self.mail.link_base_url.starts_with("https://")
+        {
+            problems.push("mail.link_base_url must be an http(s) URL".to_owned());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError(problems))
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms)
+    }
+
+    pub fn upload_timeout(&self) -> Duration {
+        Duration::from_millis(self.upload_timeout_ms)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+}
@@ -1,11 +1,18 @@
-use std::env;
+use std::time::Duration;
 
-use sea_orm::{Database, DatabaseConnection};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 
-pub async fn init_db() -> DatabaseConnection {
-    match Database::connect(env::var("DATABASE_URL").expect("DATABASE_URL is not set in .env file"))
-        .await
-    {
+use crate::config::DatabaseConfig;
+
+pub async fn init_db(config: &DatabaseConfig) -> DatabaseConnection {
+    let mut options = ConnectOptions::new(&config.url);
+    options
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs));
+
+    match Database::connect(options).await {
         Ok(db) => db,
         Err(e) => panic!("Error connecting to database: {}", e),
     }
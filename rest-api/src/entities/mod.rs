@@ -2,6 +2,17 @@
 
 pub mod prelude;
 
+pub mod audit_log;
+pub mod cart_item;
 pub mod category;
+pub mod login_challenge;
+pub mod order_line;
+pub mod orders;
 pub mod product;
+pub mod product_image;
+pub mod recovery_code;
+pub mod sea_orm_active_enums;
+pub mod session;
+pub mod stock_movement;
+pub mod user_token;
 pub mod users;
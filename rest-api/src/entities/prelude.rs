@@ -1,5 +1,15 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
+pub use super::audit_log::Entity as AuditLog;
+pub use super::cart_item::Entity as CartItem;
 pub use super::category::Entity as Category;
+pub use super::login_challenge::Entity as LoginChallenge;
+pub use super::order_line::Entity as OrderLine;
+pub use super::orders::Entity as Orders;
 pub use super::product::Entity as Product;
+pub use super::product_image::Entity as ProductImage;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::session::Entity as Session;
+pub use super::stock_movement::Entity as StockMovement;
+pub use super::user_token::Entity as UserToken;
 pub use super::users::Entity as Users;
@@ -3,27 +3,39 @@
 use sea_orm::entity::prelude::*;
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = Product)]
 #[sea_orm(table_name = "product")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub title: String,
     pub price: i32,
-    pub category: String,
+    pub category_id: i32,
+    pub stock: i32,
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::cart_item::Entity")]
+    CartItem,
+    #[sea_orm(has_many = "super::order_line::Entity")]
+    OrderLine,
     #[sea_orm(
         belongs_to = "super::category::Entity",
-        from = "Column::Category",
-        to = "super::category::Column::Name",
+        from = "Column::CategoryId",
+        to = "super::category::Column::Id",
         on_update = "NoAction",
         on_delete = "NoAction"
     )]
     Category,
+    #[sea_orm(has_many = "super::product_image::Entity")]
+    ProductImage,
+    #[sea_orm(has_many = "super::stock_movement::Entity")]
+    StockMovement,
 }
 
 impl Related<super::category::Entity> for Entity {
@@ -32,4 +44,28 @@ impl Related<super::category::Entity> for Entity {
     }
 }
 
+impl Related<super::product_image::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductImage.def()
+    }
+}
+
+impl Related<super::stock_movement::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StockMovement.def()
+    }
+}
+
+impl Related<super::cart_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CartItem.def()
+    }
+}
+
+impl Related<super::order_line::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrderLine.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
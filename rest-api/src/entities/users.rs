@@ -1,19 +1,92 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
+use super::sea_orm_active_enums::Role;
 use sea_orm::entity::prelude::*;
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = User)]
 #[sea_orm(table_name = "users")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub username: String,
     pub password: String,
+    pub role: Role,
+    pub deleted_at: Option<DateTime>,
+    #[sea_orm(unique)]
+    pub email: Option<String>,
+    pub email_verified_at: Option<DateTime>,
+    /// Base32 TOTP secret; set on enrollment, in use once `totp_enabled_at` is.
+    #[serde(skip)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime>,
+    #[serde(skip)]
+    pub totp_last_step: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::audit_log::Entity")]
+    AuditLog,
+    #[sea_orm(has_many = "super::cart_item::Entity")]
+    CartItem,
+    #[sea_orm(has_many = "super::login_challenge::Entity")]
+    LoginChallenge,
+    #[sea_orm(has_many = "super::orders::Entity")]
+    Orders,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
+    #[sea_orm(has_many = "super::session::Entity")]
+    Session,
+    #[sea_orm(has_many = "super::stock_movement::Entity")]
+    StockMovement,
+    #[sea_orm(has_many = "super::user_token::Entity")]
+    UserToken,
+}
+
+impl Related<super::session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl Related<super::stock_movement::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StockMovement.def()
+    }
+}
+
+impl Related<super::cart_item::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CartItem.def()
+    }
+}
+
+impl Related<super::orders::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Orders.def()
+    }
+}
+
+impl Related<super::login_challenge::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoginChallenge.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
+impl Related<super::user_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserToken.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
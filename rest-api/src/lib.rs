@@ -0,0 +1,6 @@
+pub mod api;
+pub mod app;
+pub mod config;
+pub mod db;
+pub mod entities;
+pub mod utils;
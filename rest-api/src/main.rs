@@ -1,68 +1,104 @@
-mod api;
-mod db;
-mod entities;
-mod utils;
-
-use std::time::Duration;
-
-use axum::{
-    middleware,
-    routing::{get, post},
-    Router,
-};
-use tower_http::{compression::CompressionLayer, timeout::TimeoutLayer, trace::TraceLayer};
-use tracing::info;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
-
-use api::auth::login;
-use api::category::{delete_category, get_category, post_category};
-use api::product::{delete_product, get_product, post_product, put_product};
-use api::text::text;
-use api::users::{delete_user, get_users, post_user, put_user};
+use std::{net::SocketAddr, time::Duration};
 
-use db::init_db;
+use tokio::{signal, sync::oneshot};
+use tracing::{info, warn};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-use utils::jwt::authenticate;
+use axum_project::app::create_app;
+use axum_project::config::{Config, LogFormat};
+use axum_project::db::init_db;
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
 
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .init();
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprint!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log.filter));
+    let registry = tracing_subscriber::registry().with(filter);
+    match config.log.format {
+        LogFormat::Pretty => registry.with(fmt::layer()).init(),
+        LogFormat::Json => registry.with(fmt::layer().json()).init(),
+    }
 
     info!("Connecting to DB...");
-    let conn = init_db().await;
-
-    info!("Starting server...");
-    let app = Router::new()
-        .route("/users", get(get_users).put(put_user).delete(delete_user))
-        .route(
-            "/category",
-            get(get_category)
-                .post(post_category)
-                .delete(delete_category),
-        )
-        .route(
-            "/product",
-            get(get_product)
-                .post(post_product)
-                .put(put_product)
-                .delete(delete_product),
+    let conn = init_db(&config.database).await;
+
+    info!("Starting server on {}...", config.server.bind_address);
+    let listener = tokio::net::TcpListener::bind(&config.server.bind_address)
+        .await
+        .unwrap();
+    let shutdown_timeout = config.server.shutdown_timeout();
+    let app = create_app(conn.clone(), config);
+
+    serve(listener, app, shutdown_timeout).await;
+
+    info!("Closing DB connections...");
+    if let Err(err) = conn.close().await {
+        warn!("Error closing database connection: {}", err);
+    }
+}
+
+/// Serves until SIGINT/SIGTERM, then gives in-flight requests `drain_timeout`
+/// to finish before dropping them.
+async fn serve(listener: tokio::net::TcpListener, app: axum::Router, drain_timeout: Duration) {
+    let (signalled_tx, signalled_rx) = oneshot::channel();
+
+    let server = async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
         )
-        .route_layer(middleware::from_fn(authenticate))
-        .route("/auth/login", post(login))
-        .route("/auth/signup", post(post_user))
-        .route("/text", get(text))
-        .with_state(conn)
-        .layer(TimeoutLayer::new(Duration::from_millis(1000)))
-        .layer(TraceLayer::new_for_http())
-        .layer(CompressionLayer::new());
-
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8000")
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            info!("Shutting down, draining in-flight requests...");
+            let _ = signalled_tx.send(());
+        })
         .await
         .unwrap();
-    axum::serve(listener, app).await.unwrap();
+    };
+
+    let deadline = async move {
+        if signalled_rx.await.is_ok() {
+            tokio::time::sleep(drain_timeout).await;
+        } else {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    tokio::select! {
+        _ = server => info!("Server stopped"),
+        _ = deadline => warn!("Drain timeout elapsed, dropping remaining connections"),
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
@@ -1,21 +1,218 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use std::borrow::Cow;
 
+use axum::{
+    body::Body,
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{JsonRejection, QueryRejection},
+    },
+    http::{HeaderName, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sea_orm::{DbErr, SqlErr};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tracing::error;
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Debug)]
 pub struct AppError {
-    code: StatusCode,
+    status: StatusCode,
+    code: Cow<'static, str>,
     message: String,
+    details: Option<Value>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+/// The JSON envelope every error response is rendered as.
+#[derive(Clone, Serialize, ToSchema)]
+pub struct ErrorBody {
+    #[schema(value_type = String, example = "not_found")]
+    pub code: Cow<'static, str>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
+    pub request_id: Option<String>,
 }
 
 impl AppError {
     pub fn new(code: StatusCode, message: impl Into<String>) -> Self {
         Self {
-            code,
+            status: code,
+            code: default_code(code),
             message: message.into(),
+            details: None,
+            headers: Vec::new(),
         }
     }
+
+    /// Overrides the machine-readable code derived from the status.
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Cow::Borrowed(code);
+        self
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Adds a response header, e.g. `Retry-After` on 429s.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+/// Derives a snake_case code such as `not_found` from the status' reason phrase.
+fn default_code(status: StatusCode) -> Cow<'static, str> {
+    let reason = status.canonical_reason().unwrap_or("error");
+
+    Cow::Owned(
+        reason
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == ' ' || *c == '-')
+            .map(|c| match c {
+                ' ' | '-' => '_',
+                c => c.to_ascii_lowercase(),
+            })
+            .collect(),
+    )
 }
 
 impl IntoResponse for AppError {
-    fn into_response(self) -> axum::response::Response {
-        (self.code, Json(self.message.clone())).into_response()
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.code,
+            message: self.message,
+            details: self.details,
+            request_id: None,
+        };
+
+        let mut response = (self.status, Json(body.clone())).into_response();
+        response.headers_mut().extend(self.headers);
+        response.extensions_mut().insert(body);
+        response
+    }
+}
+
+/// Fills in `request_id` of error bodies produced by [`AppError`].
+///
+/// Must run inside the layer that sets the `x-request-id` header.
+pub async fn attach_request_id(request: Request<Body>, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    let response = next.run(request).await;
+
+    match response.extensions().get::<ErrorBody>().cloned() {
+        Some(mut body) if request_id.is_some() => {
+            body.request_id = request_id;
+            let (mut parts, _) = response.into_parts();
+            parts.headers.remove(axum::http::header::CONTENT_LENGTH);
+            let rendered = Json(body).into_response();
+            Response::from_parts(parts, rendered.into_body())
+        }
+        _ => response,
+    }
+}
+
+impl From<DbErr> for AppError {
+    fn from(err: DbErr) -> Self {
+        match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(detail)) => {
+                AppError::new(StatusCode::CONFLICT, "Resource already exists")
+                    .with_details(Value::String(detail))
+            }
+            Some(SqlErr::ForeignKeyConstraintViolation(detail)) => AppError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Referenced resource is missing or still in use",
+            )
+            .with_code("foreign_key_violation")
+            .with_details(Value::String(detail)),
+            _ => match err {
+                DbErr::RecordNotFound(message) => AppError::new(StatusCode::NOT_FOUND, message),
+                DbErr::RecordNotUpdated => AppError::new(StatusCode::NOT_FOUND, "Record not found"),
+                err => {
+                    error!("Database error: {:?}", err);
+                    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+                        .with_code("database_error")
+                }
+            },
+        }
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match rejection {
+            JsonRejection::JsonDataError(_) => "invalid_body",
+            JsonRejection::JsonSyntaxError(_) => "malformed_json",
+            JsonRejection::MissingJsonContentType(_) => "unsupported_media_type",
+            _ => "bad_request",
+        };
+
+        AppError::new(rejection.status(), rejection.body_text()).with_code(code)
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::new(rejection.status(), rejection.body_text()).with_code("invalid_query")
+    }
+}
+
+impl From<MultipartRejection> for AppError {
+    fn from(rejection: MultipartRejection) -> Self {
+        AppError::new(rejection.status(), rejection.body_text()).with_code("invalid_multipart")
+    }
+}
+
+impl From<MultipartError> for AppError {
+    fn from(err: MultipartError) -> Self {
+        AppError::new(err.status(), err.body_text()).with_code("invalid_multipart")
+    }
+}
+
+/// Messages per field in the shape 422 responses report them, e.g.
+/// `{"price": [{"code": "range", "message": "must not be negative"}]}`.
+pub fn field_errors(errors: &ValidationErrors) -> Value {
+    let fields: Map<String, Value> = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| {
+                    json!({
+                        "code": error.code,
+                        "message": error
+                            .message
+                            .clone()
+                            .unwrap_or_else(|| error.code.clone()),
+                    })
+                })
+                .collect();
+            (field.to_string(), Value::Array(messages))
+        })
+        .collect();
+
+    Value::Object(fields)
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::new(StatusCode::UNPROCESSABLE_ENTITY, "Validation failed")
+            .with_code("validation_failed")
+            .with_details(json!({ "fields": field_errors(&errors) }))
     }
 }
@@ -0,0 +1,70 @@
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ActiveValue, ConnectionTrait};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{app_error::AppError, jwt::Claims};
+use crate::entities::{
+    audit_log::ActiveModel,
+    sea_orm_active_enums::{AuditAction, AuditEntity},
+};
+
+/// Who made a change, as recorded in the audit log.
+pub struct Actor {
+    id: i32,
+    username: String,
+}
+
+impl Actor {
+    pub fn new(id: i32, username: impl Into<String>) -> Self {
+        Self {
+            id,
+            username: username.into(),
+        }
+    }
+}
+
+impl From<&Claims> for Actor {
+    fn from(claims: &Claims) -> Self {
+        Self::new(claims.sub, claims.username.clone())
+    }
+}
+
+/// A row as stored in the `before` and `after` columns.
+pub fn snapshot<T: Serialize>(row: &T) -> Value {
+    serde_json::to_value(row).unwrap_or(Value::Null)
+}
+
+/// One change to record: what was done to which row, and the row before and
+/// after it.
+pub struct Change {
+    pub action: AuditAction,
+    pub entity: AuditEntity,
+    pub entity_id: i32,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Appends `change` to the audit log. Call it on the transaction making the
+/// change, so the entry commits or rolls back with it.
+pub async fn record<C: ConnectionTrait>(
+    conn: &C,
+    actor: &Actor,
+    change: Change,
+) -> Result<(), AppError> {
+    ActiveModel {
+        id: ActiveValue::NotSet,
+        actor_id: ActiveValue::Set(Some(actor.id)),
+        actor: ActiveValue::Set(actor.username.clone()),
+        action: ActiveValue::Set(change.action),
+        entity: ActiveValue::Set(change.entity),
+        entity_id: ActiveValue::Set(change.entity_id),
+        before: ActiveValue::Set(change.before),
+        after: ActiveValue::Set(change.after),
+        created_at: ActiveValue::Set(Utc::now().naive_utc()),
+    }
+    .insert(conn)
+    .await?;
+
+    Ok(())
+}
@@ -1,23 +1,153 @@
 use super::app_error::AppError;
+use super::metrics::time_password_hash;
+use crate::config::{AuthConfig, PasswordAlgorithm};
+use argon2::{
+    password_hash::{
+        self, rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString,
+    },
+    Algorithm, Argon2, Params, Version,
+};
 use axum::http::StatusCode;
-use bcrypt::{hash, verify};
+use bcrypt::HashParts;
+use std::sync::Arc;
+use tokio::task::spawn_blocking;
 use tracing::error;
 
-const COST: u32 = 12;
+/// Hashes passwords with one algorithm and its current parameters.
+pub trait PasswordHasher: Send + Sync {
+    fn hash(&self, password: &str) -> Result<String, AppError>;
 
-pub fn hash_password(password: &str) -> Result<String, AppError> {
-    hash(password, COST).map_err(|err| {
-        error!("Error hashing password: {:?}", err);
-        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error securing password")
-    })
+    /// Whether `hash` was made by another algorithm or with other parameters,
+    /// and should be replaced by a fresh hash of the same password.
+    fn needs_rehash(&self, hash: &str) -> bool;
 }
 
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
-    verify(password, hash).map_err(|err| {
-        error!("Error verifying password: {:?}", err);
-        AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "The was a problem verifying your password",
-        )
-    })
+/// The hasher `config` selects.
+pub fn hasher(config: &AuthConfig) -> Box<dyn PasswordHasher> {
+    match config.password_hasher {
+        PasswordAlgorithm::Argon2id => Box::new(Argon2idHasher::new(
+            // `Config::validate` has checked the parameters.
+            Params::new(
+                config.argon2.memory_kib,
+                config.argon2.iterations,
+                config.argon2.parallelism,
+                None,
+            )
+            .unwrap_or_default(),
+        )),
+        PasswordAlgorithm::Bcrypt => Box::new(BcryptHasher::new(config.bcrypt_cost)),
+    }
+}
+
+pub struct Argon2idHasher {
+    params: Params,
+}
+
+impl Argon2idHasher {
+    pub fn new(params: Params) -> Self {
+        Self { params }
+    }
+}
+
+impl PasswordHasher for Argon2idHasher {
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone());
+        let salt = SaltString::generate(&mut OsRng);
+
+        time_password_hash("hash", || argon2.hash_password(password.as_bytes(), &salt))
+            .map(|hash| hash.to_string())
+            .map_err(hash_error)
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+
+        parsed.algorithm != Algorithm::Argon2id.ident()
+            || parsed.version != Some(Version::V0x13.into())
+            || Params::try_from(&parsed).map_or(true, |params| {
+                (params.m_cost(), params.t_cost(), params.p_cost())
+                    != (
+                        self.params.m_cost(),
+                        self.params.t_cost(),
+                        self.params.p_cost(),
+                    )
+            })
+    }
+}
+
+pub struct BcryptHasher {
+    cost: u32,
+}
+
+impl BcryptHasher {
+    pub fn new(cost: u32) -> Self {
+        Self { cost }
+    }
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        time_password_hash("hash", || bcrypt::hash(password, self.cost)).map_err(hash_error)
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        hash.parse::<HashParts>()
+            .map_or(true, |parts| parts.get_cost() != self.cost)
+    }
+}
+
+fn hash_error(err: impl std::fmt::Debug) -> AppError {
+    error!("Error hashing password: {:?}", err);
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error securing password")
+}
+
+fn verify_error(err: impl std::fmt::Debug) -> AppError {
+    error!("Error verifying password: {:?}", err);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "The was a problem verifying your password",
+    )
+}
+
+/// Hashes with `hasher` on the blocking thread pool; a hash takes tens of
+/// milliseconds, far too long to hold up an async worker.
+pub async fn hash_password(
+    hasher: &Arc<dyn PasswordHasher>,
+    password: &str,
+) -> Result<String, AppError> {
+    let hasher = hasher.clone();
+    let password = password.to_owned();
+
+    spawn_blocking(move || hasher.hash(&password))
+        .await
+        .map_err(hash_error)?
+}
+
+/// Checks a password against a hash of any supported algorithm, telling them
+/// apart by the hash's format. The parameters are read from the hash. Runs on
+/// the blocking thread pool like [`hash_password`].
+pub async fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
+    let password = password.to_owned();
+    let hash = hash.to_owned();
+
+    spawn_blocking(move || verify_blocking(&password, &hash))
+        .await
+        .map_err(verify_error)?
+}
+
+fn verify_blocking(password: &str, hash: &str) -> Result<bool, AppError> {
+    if hash.starts_with("$argon2") {
+        let parsed = PasswordHash::new(hash).map_err(verify_error)?;
+        return match time_password_hash("verify", || {
+            Argon2::default().verify_password(password.as_bytes(), &parsed)
+        }) {
+            Ok(()) => Ok(true),
+            Err(password_hash::Error::Password) => Ok(false),
+            Err(err) => Err(verify_error(err)),
+        };
+    }
+
+    time_password_hash("verify", || bcrypt::verify(password, hash)).map_err(verify_error)
 }
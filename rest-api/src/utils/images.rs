@@ -0,0 +1,107 @@
+use std::io::Cursor;
+
+use axum::http::StatusCode;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use serde_json::json;
+
+use super::app_error::AppError;
+
+/// Images wider or taller than this are rejected before being decoded.
+const MAX_DIMENSION: u32 = 8192;
+
+/// Formats that may be uploaded, with their content type and file extension.
+const ALLOWED: &[(ImageFormat, &str, &str)] = &[
+    (ImageFormat::Jpeg, "image/jpeg", "jpg"),
+    (ImageFormat::Png, "image/png", "png"),
+    (ImageFormat::WebP, "image/webp", "webp"),
+];
+
+/// A validated upload and its thumbnail.
+pub struct ProcessedImage {
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail: Vec<u8>,
+    pub thumbnail_content_type: &'static str,
+    pub thumbnail_extension: &'static str,
+}
+
+fn unsupported(message: impl Into<String>) -> AppError {
+    AppError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, message).with_details(json!({
+        "allowed": ALLOWED.iter().map(|(_, content_type, _)| *content_type).collect::<Vec<_>>()
+    }))
+}
+
+/// Checks that `bytes` really are an image of the declared type, decodes it
+/// and renders a thumbnail no larger than `thumbnail_px` on either side.
+///
+/// Decoding is CPU-bound; call this from `spawn_blocking`.
+pub fn process_image(
+    bytes: &[u8],
+    declared: Option<&str>,
+    thumbnail_px: u32,
+) -> Result<ProcessedImage, AppError> {
+    let declared = declared.ok_or_else(|| unsupported("Image content type is missing"))?;
+    let &(format, content_type, extension) = ALLOWED
+        .iter()
+        .find(|(_, content_type, _)| *content_type == declared)
+        .ok_or_else(|| unsupported(format!("Unsupported image type {}", declared)))?;
+
+    // The declared type is client-controlled, so sniff the actual bytes too.
+    if image::guess_format(bytes).ok() != Some(format) {
+        return Err(unsupported(format!(
+            "Image content does not match {}",
+            declared
+        )));
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|err| {
+        AppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Image could not be decoded",
+        )
+        .with_code("invalid_image")
+        .with_details(json!(err.to_string()))
+    })?;
+
+    let thumbnail = image.thumbnail(thumbnail_px, thumbnail_px);
+    // JPEG has no alpha channel; everything else keeps it in a PNG.
+    let (thumbnail, thumbnail_format, thumbnail_content_type, thumbnail_extension) = match format {
+        ImageFormat::Jpeg => (
+            DynamicImage::ImageRgb8(thumbnail.to_rgb8()),
+            ImageFormat::Jpeg,
+            "image/jpeg",
+            "jpg",
+        ),
+        _ => (thumbnail, ImageFormat::Png, "image/png", "png"),
+    };
+
+    let mut encoded = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut encoded), thumbnail_format)
+        .map_err(|err| {
+            AppError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Thumbnail could not be created",
+            )
+            .with_code("invalid_image")
+            .with_details(json!(err.to_string()))
+        })?;
+
+    Ok(ProcessedImage {
+        content_type,
+        extension,
+        width: image.width(),
+        height: image.height(),
+        thumbnail: encoded,
+        thumbnail_content_type,
+        thumbnail_extension,
+    })
+}
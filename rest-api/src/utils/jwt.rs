@@ -1,76 +1,88 @@
 use super::app_error::AppError;
+use super::signing_keys::SigningKeys;
+use crate::config::AuthConfig;
+use crate::entities::{sea_orm_active_enums::Role, users};
 use axum::{
+    async_trait,
     body::Body,
-    http::{HeaderMap, Request, StatusCode},
+    extract::{FromRequestParts, State},
+    http::{request::Parts, HeaderMap, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
 use chrono::Duration;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::sync::Arc;
 use tracing::{debug, error};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Claims {
-    exp: usize,
-    username: String,
+    pub exp: usize,
+    pub sub: i32,
+    pub username: String,
+    pub role: Role,
 }
 
-lazy_static! {
-    static ref SECRET_KEY: String = env::var("SECRET_KEY").expect("SECRET_KEY must be set");
+impl Claims {
+    /// Whether the caller may act on the user with the given id.
+    pub fn can_modify_user(&self, user_id: i32) -> bool {
+        self.sub == user_id || self.role == Role::Admin
+    }
+}
+
+/// Extracts the claims `authenticate` validated for this request.
+#[async_trait]
+impl<S> FromRequestParts<S> for Claims
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "Not authenticated!"))
+    }
 }
 
-pub fn create_token(username: String) -> Result<String, AppError> {
+pub fn create_token(
+    user: &users::Model,
+    keys: &SigningKeys,
+    auth: &AuthConfig,
+) -> Result<String, AppError> {
     let now = chrono::Utc::now();
-    let expires_at = now + Duration::hours(1);
+    let expires_at = now + Duration::minutes(auth.access_token_minutes);
     let exp = expires_at.timestamp() as usize;
-    let claims = Claims { exp, username };
-    let token_header = Header::default();
-    let key = EncodingKey::from_secret(SECRET_KEY.as_bytes());
-
-    encode(&token_header, &claims, &key).map_err(|err| {
-        error!("Error creating token: {:?}", err);
-        AppError::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "There was an error, please try again later",
-        )
-    })
+    let claims = Claims {
+        exp,
+        sub: user.id,
+        username: user.username.clone(),
+        role: user.role,
+    };
+
+    keys.sign(&claims)
 }
 
-pub fn validate_token(token: &str) -> Result<Claims, AppError> {
-    decode::<Claims>(
-        &token.replace("Bearer ", ""),
-        &DecodingKey::from_secret(SECRET_KEY.as_bytes()),
-        &Validation::new(jsonwebtoken::Algorithm::HS256),
-    )
-    .map_err(|err| match err.kind() {
-        jsonwebtoken::errors::ErrorKind::InvalidToken
-        | jsonwebtoken::errors::ErrorKind::InvalidSignature
-        | jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-            AppError::new(StatusCode::UNAUTHORIZED, "not authenticated!")
-        }
-        _ => {
-            error!("Error validating token: {:?}", err);
-            AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error validating token")
-        }
-    })
-    .and_then(|decoded| {
-        if chrono::Utc::now().timestamp() > decoded.claims.exp as i64 {
-            Err(AppError::new(
-                StatusCode::UNAUTHORIZED,
-                "not authenticated!",
-            ))
-        } else {
-            Ok(decoded.claims)
-        }
-    })
+pub fn validate_token(token: &str, keys: &SigningKeys) -> Result<Claims, AppError> {
+    keys.verify(&token.replace("Bearer ", ""))
+        .and_then(|claims| {
+            if chrono::Utc::now().timestamp() > claims.exp as i64 {
+                Err(AppError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "not authenticated!",
+                ))
+            } else {
+                Ok(claims)
+            }
+        })
 }
 
 pub async fn authenticate(
+    State(keys): State<Arc<SigningKeys>>,
     headers: HeaderMap,
-    request: Request<Body>,
+    mut request: Request<Body>,
     next: Next,
 ) -> Result<Response, AppError> {
     if let Some(value) = headers.get("Authorization") {
@@ -79,7 +91,7 @@ pub async fn authenticate(
             AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error reading token")
         })?;
 
-        let claim = validate_token(token)?;
+        let claim = validate_token(token, &keys)?;
 
         debug!("Authenticated user: {}", claim.username);
 
@@ -87,6 +99,8 @@ pub async fn authenticate(
             return Err(AppError::new(StatusCode::UNAUTHORIZED, "Token has expired"));
         }
 
+        request.extensions_mut().insert(claim);
+
         Ok(next.run(request).await)
     } else {
         Err(AppError::new(
@@ -95,3 +109,31 @@ pub async fn authenticate(
         ))
     }
 }
+
+/// Rejects the request unless the caller's role is at least `role`.
+///
+/// Must be layered inside `authenticate`, e.g.
+/// `delete(handler.layer(middleware::from_fn_with_state(Role::Admin, require_role)))`.
+pub async fn require_role(
+    State(role): State<Role>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let claim = request
+        .extensions()
+        .get::<Claims>()
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "Not authenticated!"))?;
+
+    if claim.role < role {
+        debug!(
+            "User {} with role {:?} denied, requires {:?}",
+            claim.username, claim.role, role
+        );
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Insufficient permissions",
+        ));
+    }
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::http::{header::RETRY_AFTER, HeaderValue, StatusCode};
+use rand::RngCore;
+use tracing::warn;
+
+use super::{
+    app_error::AppError,
+    hash::{hash_password, verify_password, PasswordHasher},
+};
+use crate::config::LockoutConfig;
+
+/// Entries are pruned once the map grows past this many keys.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    User(String),
+    Ip(IpAddr),
+}
+
+struct Attempts {
+    failures: u32,
+    last_failure: Instant,
+    blocked_until: Instant,
+}
+
+/// Tracks failed logins per username and per client IP.
+///
+/// Every failure blocks further attempts for an exponentially growing delay;
+/// reaching the configured maximum locks the key out entirely.
+pub struct LoginLockout {
+    config: LockoutConfig,
+    attempts: Mutex<HashMap<Key, Attempts>>,
+    dummy_hash: OnceLock<String>,
+}
+
+impl LoginLockout {
+    pub fn new(config: LockoutConfig) -> Self {
+        Self {
+            config,
+            attempts: Mutex::new(HashMap::new()),
+            dummy_hash: OnceLock::new(),
+        }
+    }
+
+    fn keys(username: &str, ip: Option<IpAddr>) -> impl Iterator<Item = Key> {
+        std::iter::once(Key::User(username.to_owned())).chain(ip.map(Key::Ip))
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.config.lockout_secs)
+    }
+
+    /// Rejects the attempt with 429 while the username or IP is blocked.
+    pub fn check(&self, username: &str, ip: Option<IpAddr>) -> Result<(), AppError> {
+        let now = Instant::now();
+        let attempts = self.attempts.lock().unwrap();
+
+        let retry_after = Self::keys(username, ip)
+            .filter_map(|key| attempts.get(&key))
+            .filter(|entry| entry.blocked_until > now)
+            .map(|entry| entry.blocked_until - now)
+            .max();
+
+        match retry_after {
+            Some(wait) => Err(AppError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many failed login attempts, try again later",
+            )
+            .with_code("login_locked")
+            .with_header(
+                RETRY_AFTER,
+                HeaderValue::from(wait.as_secs_f64().ceil() as u64),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_failure(&self, username: &str, ip: Option<IpAddr>) {
+        let now = Instant::now();
+        let window = self.window();
+        let mut attempts = self.attempts.lock().unwrap();
+
+        if attempts.len() > PRUNE_THRESHOLD {
+            attempts.retain(|_, entry| now.duration_since(entry.last_failure) < window);
+        }
+
+        for key in Self::keys(username, ip) {
+            let (max_attempts, ip) = match &key {
+                Key::User(_) => (self.config.max_attempts_per_user, None),
+                Key::Ip(ip) => (self.config.max_attempts_per_ip, Some(*ip)),
+            };
+            let entry = attempts.entry(key).or_insert(Attempts {
+                failures: 0,
+                last_failure: now,
+                blocked_until: now,
+            });
+            // Failures older than the window are forgiven.
+            if now.duration_since(entry.last_failure) >= window {
+                entry.failures = 0;
+            }
+            entry.failures += 1;
+            entry.last_failure = now;
+
+            let delay = if entry.failures >= max_attempts {
+                match ip {
+                    Some(ip) => warn!("Locking out {} after failed logins", ip),
+                    None => warn!("Locking out username {} after failed logins", username),
+                }
+                window
+            } else {
+                let backoff = Duration::from_millis(self.config.backoff_base_ms);
+                (backoff * 2u32.saturating_pow(entry.failures - 1)).min(window)
+            };
+            entry.blocked_until = now + delay;
+        }
+    }
+
+    /// Forgets the username's failures after a successful login.
+    pub fn record_success(&self, username: &str) {
+        self.unlock(username);
+    }
+
+    /// Lifts a username's lockout; returns whether it had any failures recorded.
+    pub fn unlock(&self, username: &str) -> bool {
+        self.attempts
+            .lock()
+            .unwrap()
+            .remove(&Key::User(username.to_owned()))
+            .is_some()
+    }
+
+    /// Verifies the password against a throwaway hash made by `hasher`, so
+    /// unknown usernames take as long to reject as wrong passwords.
+    pub async fn verify_dummy(
+        &self,
+        password: &str,
+        hasher: &Arc<dyn PasswordHasher>,
+    ) -> Result<bool, AppError> {
+        let hash = match self.dummy_hash.get() {
+            Some(hash) => hash,
+            None => {
+                let mut secret = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut secret);
+                let hash = hash_password(hasher, &hex::encode(secret)).await?;
+                self.dummy_hash.get_or_init(|| hash)
+            }
+        };
+
+        verify_password(password, hash).await
+    }
+}
@@ -0,0 +1,159 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{async_trait, http::StatusCode};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use tracing::{error, info};
+
+use super::app_error::AppError;
+use crate::config::{MailConfig, MailTransport, SmtpTls};
+
+/// A plain-text mail to one recipient.
+#[derive(Clone, Debug)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers mail; implement this to use e.g. a provider's HTTP API.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), AppError>;
+}
+
+/// The mailer `config` selects.
+pub fn mailer(config: &MailConfig) -> Result<Box<dyn Mailer>, String> {
+    let from = config
+        .from
+        .parse::<Mailbox>()
+        .map_err(|err| format!("from `{}` is not a valid address: {}", config.from, err))?;
+
+    Ok(match config.transport {
+        MailTransport::Log => Box::new(LogMailer),
+        MailTransport::File => Box::new(FileMailer::new(config.dir.clone(), from)),
+        MailTransport::Smtp => Box::new(SmtpMailer::new(config, from)?),
+    })
+}
+
+/// Sends in the background, so the response neither waits for the mail
+/// server nor reveals through its timing whether a mail was sent. Failures
+/// are only logged.
+pub fn send_in_background(mailer: Arc<dyn Mailer>, email: Email) {
+    tokio::spawn(async move {
+        let _ = mailer.send(&email).await;
+    });
+}
+
+fn mail_error(message: impl std::fmt::Display) -> AppError {
+    error!("Sending mail failed: {}", message);
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error sending mail").with_code("mail_error")
+}
+
+fn message(from: &Mailbox, email: &Email) -> Result<Message, AppError> {
+    let to = email.to.parse::<Mailbox>().map_err(mail_error)?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body.clone())
+        .map_err(mail_error)
+}
+
+/// Logs every mail, links included, instead of sending it. Only for local
+/// development.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        info!(to = %email.to, subject = %email.subject, "Mail not sent:\n{}", email.body);
+        Ok(())
+    }
+}
+
+/// Writes every mail to its own `.eml` file, for development and tests.
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: PathBuf, from: Mailbox) -> Self {
+        Self { dir, from }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        let message = message(&self.from, email)?;
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(mail_error)?;
+
+        // Sorts by the time it was written.
+        let name = format!(
+            "{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            hex::encode(rand::random::<[u8; 4]>())
+        );
+        tokio::fs::write(self.dir.join(name), message.formatted())
+            .await
+            .map_err(mail_error)
+    }
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Doesn't connect yet; that happens when the first mail is sent.
+    pub fn new(config: &MailConfig, from: Mailbox) -> Result<Self, String> {
+        let smtp = &config.smtp;
+        if smtp.host.is_empty() {
+            return Err("smtp.host must be set for the smtp transport".to_owned());
+        }
+
+        let mut builder = match smtp.tls {
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host),
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host),
+            SmtpTls::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &smtp.host,
+            )),
+        }
+        .map_err(|err| format!("smtp: {}", err))?
+        .port(smtp.port);
+        if !smtp.username.is_empty() {
+            builder = builder.credentials(Credentials::new(
+                smtp.username.clone(),
+                smtp.password.clone(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        let message = message(&self.from, email)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(mail_error)
+    }
+}
@@ -0,0 +1,108 @@
+use std::{sync::OnceLock, time::Instant};
+
+use ::metrics::{counter, gauge, histogram, Gauge};
+use axum::{body::Body, extract::MatchedPath, http::Request, middleware::Next, response::Response};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sea_orm::metric::Info;
+
+const HTTP_REQUESTS_TOTAL: &str = "http_requests_total";
+const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+const HTTP_REQUESTS_IN_FLIGHT: &str = "http_requests_in_flight";
+const DB_QUERY_DURATION: &str = "db_query_duration_seconds";
+const LOGIN_ATTEMPTS_TOTAL: &str = "auth_login_attempts_total";
+const PASSWORD_HASH_DURATION: &str = "password_hash_duration_seconds";
+
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Installs the global Prometheus recorder on first use and returns its handle.
+pub fn recorder() -> PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+    HANDLE
+        .get_or_init(|| {
+            PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Suffix("duration_seconds".to_owned()),
+                    DURATION_BUCKETS,
+                )
+                .expect("duration buckets must not be empty")
+                .install_recorder()
+                .expect("failed to install Prometheus recorder")
+        })
+        .clone()
+}
+
+/// Decrements the in-flight gauge even if the request future is dropped,
+/// e.g. by the timeout layer.
+struct InFlight(Gauge);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.decrement(1.0);
+    }
+}
+
+/// Records count, latency and in-flight requests per route.
+///
+/// Must be added with `route_layer` so the matched path is known.
+pub async fn track_metrics(request: Request<Body>, next: Next) -> Response {
+    let method = request.method().to_string();
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| request.uri().path().to_owned());
+
+    let in_flight =
+        gauge!(HTTP_REQUESTS_IN_FLIGHT, "method" => method.clone(), "path" => path.clone());
+    in_flight.increment(1.0);
+    let _in_flight = InFlight(in_flight);
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let labels = [
+        ("method", method),
+        ("path", path),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    counter!(HTTP_REQUESTS_TOTAL, &labels).increment(1);
+    histogram!(HTTP_REQUEST_DURATION, &labels).record(elapsed);
+
+    response
+}
+
+/// SeaORM metric callback timing every statement by its SQL verb.
+pub fn record_db_query(info: &Info<'_>) {
+    let operation = info
+        .statement
+        .sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    histogram!(
+        DB_QUERY_DURATION,
+        "operation" => operation,
+        "failed" => info.failed.to_string(),
+    )
+    .record(info.elapsed.as_secs_f64());
+}
+
+pub fn record_login(success: bool) {
+    let result = if success { "success" } else { "failure" };
+    counter!(LOGIN_ATTEMPTS_TOTAL, "result" => result).increment(1);
+}
+
+/// Times a password hash or verification.
+pub fn time_password_hash<T>(operation: &'static str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    histogram!(PASSWORD_HASH_DURATION, "operation" => operation)
+        .record(start.elapsed().as_secs_f64());
+    result
+}
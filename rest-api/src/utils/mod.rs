@@ -1,3 +1,18 @@
 pub mod app_error;
+pub mod audit;
+pub mod extract;
 pub mod hash;
+pub mod images;
 pub mod jwt;
+pub mod lockout;
+pub mod mailer;
+pub mod metrics;
+pub mod pagination;
+pub mod rate_limit;
+pub mod session;
+pub mod signing_keys;
+pub mod soft_delete;
+pub mod storage;
+pub mod two_factor;
+pub mod user_token;
+pub mod validation;
@@ -0,0 +1,153 @@
+use axum::http::StatusCode;
+use sea_orm::{
+    DatabaseConnection, EntityTrait, Iterable, Order, PaginatorTrait, PrimaryKeyToColumn,
+    QueryOrder, Select,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+
+use super::app_error::AppError;
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+/// Query parameters shared by every list endpoint.
+///
+/// `sort` takes a column name optionally followed by `:asc` or `:desc`,
+/// e.g. `?page=2&per_page=50&sort=price:desc`.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// 1-based page number, defaults to 1.
+    page: Option<u64>,
+    /// Items per page, between 1 and 100, defaults to 20.
+    per_page: Option<u64>,
+    /// Column to sort by, optionally suffixed with `:asc` or `:desc`.
+    sort: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+    pub next_page: Option<u64>,
+}
+
+impl<T> Page<T> {
+    /// Replaces the items, e.g. to attach related rows, keeping the counts.
+    pub fn map_items<U>(self, f: impl FnOnce(Vec<T>) -> Vec<U>) -> Page<U> {
+        Page {
+            items: f(self.items),
+            total: self.total,
+            page: self.page,
+            per_page: self.per_page,
+            total_pages: self.total_pages,
+            next_page: self.next_page,
+        }
+    }
+}
+
+impl PageParams {
+    fn page(&self) -> Result<u64, AppError> {
+        match self.page {
+            Some(0) => Err(AppError::new(StatusCode::BAD_REQUEST, "page starts at 1")),
+            page => Ok(page.unwrap_or(1)),
+        }
+    }
+
+    fn per_page(&self) -> Result<u64, AppError> {
+        match self.per_page {
+            Some(per_page) if per_page == 0 || per_page > MAX_PER_PAGE => Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                format!("per_page must be between 1 and {}", MAX_PER_PAGE),
+            )),
+            per_page => Ok(per_page.unwrap_or(DEFAULT_PER_PAGE)),
+        }
+    }
+
+    /// The page and its size, rejecting pages whose offset overflows the
+    /// database's signed 64-bit `OFFSET`.
+    fn bounds(&self) -> Result<(u64, u64), AppError> {
+        let page = self.page()?;
+        let per_page = self.per_page()?;
+
+        match (page - 1).checked_mul(per_page) {
+            Some(offset) if i64::try_from(offset).is_ok() => Ok((page, per_page)),
+            _ => Err(AppError::new(StatusCode::BAD_REQUEST, "page is too large")),
+        }
+    }
+
+    /// Whether the caller asked for a specific order.
+    pub fn has_sort(&self) -> bool {
+        self.sort.is_some()
+    }
+
+    fn order<C: Copy>(
+        &self,
+        sortable: &[(&str, C)],
+        default: &str,
+    ) -> Result<(C, Order), AppError> {
+        let sort = self.sort.as_deref().unwrap_or(default);
+        let (name, direction) = sort.split_once(':').unwrap_or((sort, "asc"));
+
+        let order = match direction {
+            "asc" => Order::Asc,
+            "desc" => Order::Desc,
+            _ => {
+                return Err(AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    "sort direction must be asc or desc",
+                ))
+            }
+        };
+
+        sortable
+            .iter()
+            .find(|(column_name, _)| *column_name == name)
+            .map(|(_, column)| (*column, order))
+            .ok_or_else(|| {
+                AppError::new(StatusCode::BAD_REQUEST, format!("cannot sort by {}", name))
+                    .with_details(json!({
+                        "allowed": sortable.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+                    }))
+            })
+    }
+}
+
+/// Runs `select` one page at a time, ordered by the requested column and then
+/// by primary key so pages stay stable.
+pub async fn paginate<E>(
+    conn: &DatabaseConnection,
+    select: Select<E>,
+    params: &PageParams,
+    sortable: &[(&str, E::Column)],
+    default_sort: &str,
+) -> Result<Page<E::Model>, AppError>
+where
+    E: EntityTrait,
+    E::Model: Sync,
+{
+    let (page, per_page) = params.bounds()?;
+    let (column, order) = params.order(sortable, default_sort)?;
+
+    let select = E::PrimaryKey::iter().fold(select.order_by(column, order), |select, key| {
+        select.order_by_asc(key.into_column())
+    });
+
+    let paginator = select.paginate(conn, per_page);
+    let counts = paginator.num_items_and_pages().await?;
+    let items = paginator.fetch_page(page - 1).await?;
+
+    Ok(Page {
+        items,
+        total: counts.number_of_items,
+        page,
+        per_page,
+        total_pages: counts.number_of_pages,
+        next_page: (page < counts.number_of_pages).then_some(page + 1),
+    })
+}
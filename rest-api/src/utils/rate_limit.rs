@@ -0,0 +1,44 @@
+use std::net::SocketAddr;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header::RETRY_AFTER, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+pub use rate_limiter::{Decision, InMemoryStore, RateLimitStore, RateLimiter};
+
+use super::{app_error::AppError, jwt::Claims};
+
+/// Charges the caller one token, keyed by the authenticated user when
+/// `authenticate` ran first and by client IP otherwise.
+///
+/// Adds `RateLimit-*` headers to every response and rejects with 429 and
+/// `Retry-After` once the bucket is empty.
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let client = match request.extensions().get::<Claims>() {
+        Some(claims) => format!("user:{}", claims.sub),
+        None => match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_owned(),
+        },
+    };
+
+    let decision = limiter.acquire(&client).await;
+
+    let mut response = match decision.retry_after {
+        Some(retry_after) => AppError::new(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded")
+            .with_code("rate_limited")
+            .with_header(RETRY_AFTER, rate_limiter::seconds(retry_after))
+            .into_response(),
+        None => next.run(request).await,
+    };
+    decision.set_headers(response.headers_mut());
+
+    response
+}
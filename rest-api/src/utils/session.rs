@@ -0,0 +1,153 @@
+use super::app_error::AppError;
+use crate::config::AuthConfig;
+use crate::entities::{
+    prelude::{Session, Users},
+    session::{ActiveModel, Column, Model},
+    users,
+};
+use axum::http::StatusCode;
+use chrono::{Duration, NaiveDateTime, Utc};
+use rand::RngCore;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
+};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+fn invalid_refresh_token() -> AppError {
+    AppError::new(StatusCode::UNAUTHORIZED, "invalid refresh token")
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// Refresh tokens and those mailed to users are opaque random strings; only
+/// their SHA-256 digest is stored.
+pub(crate) fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+async fn insert_session<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    auth: &AuthConfig,
+) -> Result<String, AppError> {
+    let token = generate_token();
+    let now = now();
+
+    ActiveModel {
+        id: ActiveValue::NotSet,
+        user_id: ActiveValue::Set(user_id),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        expires_at: ActiveValue::Set(now + Duration::days(auth.refresh_token_days)),
+        revoked_at: ActiveValue::Set(None),
+        created_at: ActiveValue::Set(now),
+    }
+    .insert(conn)
+    .await?;
+
+    Ok(token)
+}
+
+async fn find_session<C: ConnectionTrait>(conn: &C, token: &str) -> Result<Model, AppError> {
+    Session::find()
+        .filter(Column::TokenHash.eq(hash_token(token)))
+        .one(conn)
+        .await?
+        .ok_or_else(invalid_refresh_token)
+}
+
+/// Starts a new session for the user and returns its refresh token.
+pub async fn create_session(
+    conn: &DatabaseConnection,
+    user_id: i32,
+    auth: &AuthConfig,
+) -> Result<String, AppError> {
+    insert_session(conn, user_id, auth).await
+}
+
+/// Exchanges a refresh token for a new one, revoking the old session.
+///
+/// Presenting a token that was already revoked means it has leaked, so every
+/// session of its owner is revoked as well.
+pub async fn rotate_session(
+    conn: &DatabaseConnection,
+    token: &str,
+    auth: &AuthConfig,
+) -> Result<(users::Model, String), AppError> {
+    let txn = conn.begin().await?;
+
+    let session = find_session(&txn, token).await?;
+
+    if session.revoked_at.is_some() {
+        warn!("Refresh token reuse detected for user {}", session.user_id);
+        txn.rollback().await?;
+        revoke_user_sessions(conn, session.user_id).await?;
+        return Err(invalid_refresh_token());
+    }
+
+    if session.expires_at < now() {
+        return Err(invalid_refresh_token());
+    }
+
+    // Only one concurrent refresh may win the rotation.
+    let revoked = Session::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(now()))
+        .filter(Column::Id.eq(session.id))
+        .filter(Column::RevokedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    if revoked.rows_affected != 1 {
+        return Err(invalid_refresh_token());
+    }
+
+    let user = Users::find_by_id(session.user_id)
+        .filter(users::Column::DeletedAt.is_null())
+        .one(&txn)
+        .await?
+        .ok_or_else(invalid_refresh_token)?;
+
+    let refresh_token = insert_session(&txn, user.id, auth).await?;
+
+    txn.commit().await?;
+
+    Ok((user, refresh_token))
+}
+
+/// Revokes the session the refresh token belongs to.
+pub async fn revoke_session(conn: &DatabaseConnection, token: &str) -> Result<(), AppError> {
+    let session = find_session(conn, token).await?;
+
+    Session::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(now()))
+        .filter(Column::Id.eq(session.id))
+        .filter(Column::RevokedAt.is_null())
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Revokes every active session of the user.
+pub async fn revoke_user_sessions<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+) -> Result<(), AppError> {
+    Session::update_many()
+        .col_expr(Column::RevokedAt, Expr::value(now()))
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::RevokedAt.is_null())
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use simple_asn1::{from_der, oid, ASN1Block};
+use tracing::error;
+
+use super::{app_error::AppError, jwt::Claims};
+use crate::config::{AuthConfig, JwtAlgorithm, JwtKeyConfig};
+
+/// Signs access tokens with the active key and verifies them with any
+/// configured one, picked by the token's `kid`.
+pub struct SigningKeys {
+    signing: (Header, EncodingKey),
+    verifying: HashMap<String, (Algorithm, DecodingKey)>,
+    /// Verifies HS256 tokens without a `kid` while `secret_key` is set.
+    secret: Option<DecodingKey>,
+    jwks: JwkSet,
+}
+
+impl SigningKeys {
+    /// Loads the keys `auth.jwt` names, or falls back to HS256 with
+    /// `auth.secret_key` when there are none.
+    pub fn from_config(auth: &AuthConfig) -> Result<Self, String> {
+        let secret = (!auth.secret_key.is_empty())
+            .then(|| DecodingKey::from_secret(auth.secret_key.as_bytes()));
+
+        if auth.jwt.keys.is_empty() {
+            return Ok(Self {
+                signing: (
+                    Header::new(Algorithm::HS256),
+                    EncodingKey::from_secret(auth.secret_key.as_bytes()),
+                ),
+                verifying: HashMap::new(),
+                secret,
+                jwks: JwkSet { keys: Vec::new() },
+            });
+        }
+
+        let mut kids = HashSet::new();
+        let mut verifying = HashMap::new();
+        let mut jwks = Vec::new();
+        let mut signing = None;
+
+        for key in &auth.jwt.keys {
+            if key.kid.is_empty() || !kids.insert(key.kid.as_str()) {
+                return Err(format!(
+                    "key ids must be unique and not empty: `{}`",
+                    key.kid
+                ));
+            }
+            let jwk = read_public_key(key)
+                .map_err(|err| format!("{}: {}", key.public_key.display(), err))?;
+            let decoding_key = DecodingKey::from_jwk(&jwk).map_err(|err| err.to_string())?;
+
+            if key.kid == auth.jwt.signing_kid {
+                let Some(path) = &key.private_key else {
+                    return Err(format!("signing key `{}` has no private_key", key.kid));
+                };
+                let encoding_key = read_private_key(key.algorithm, path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                let mut header = Header::new(algorithm(key.algorithm));
+                header.kid = Some(key.kid.clone());
+                check_pair(&header, &encoding_key, &decoding_key)
+                    .map_err(|_| format!("private and public key of `{}` don't match", key.kid))?;
+                signing = Some((header, encoding_key));
+            }
+
+            verifying.insert(key.kid.clone(), (algorithm(key.algorithm), decoding_key));
+            jwks.push(jwk);
+        }
+
+        Ok(Self {
+            signing: signing
+                .ok_or_else(|| format!("signing_kid `{}` names no key", auth.jwt.signing_kid))?,
+            verifying,
+            secret,
+            jwks: JwkSet { keys: jwks },
+        })
+    }
+
+    pub fn sign(&self, claims: &Claims) -> Result<String, AppError> {
+        let (header, key) = &self.signing;
+
+        encode(header, claims, key).map_err(|err| {
+            error!("Error creating token: {:?}", err);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "There was an error, please try again later",
+            )
+        })
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, AppError> {
+        let not_authenticated = || AppError::new(StatusCode::UNAUTHORIZED, "not authenticated!");
+        let header = decode_header(token).map_err(|_| not_authenticated())?;
+
+        // Each key only accepts its own algorithm, so a token can't pick a
+        // weaker one.
+        let (algorithm, key) = match &header.kid {
+            Some(kid) => self
+                .verifying
+                .get(kid)
+                .map(|(algorithm, key)| (*algorithm, key)),
+            None => self.secret.as_ref().map(|key| (Algorithm::HS256, key)),
+        }
+        .ok_or_else(not_authenticated)?;
+
+        decode::<Claims>(token, key, &Validation::new(algorithm))
+            .map(|decoded| decoded.claims)
+            .map_err(|err| match err.kind() {
+                jsonwebtoken::errors::ErrorKind::InvalidToken
+                | jsonwebtoken::errors::ErrorKind::InvalidSignature
+                | jsonwebtoken::errors::ErrorKind::InvalidAlgorithm
+                | jsonwebtoken::errors::ErrorKind::ExpiredSignature => not_authenticated(),
+                _ => {
+                    error!("Error validating token: {:?}", err);
+                    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error validating token")
+                }
+            })
+    }
+
+    /// The public verification keys; empty with HS256.
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+fn algorithm(algorithm: JwtAlgorithm) -> Algorithm {
+    match algorithm {
+        JwtAlgorithm::Rs256 => Algorithm::RS256,
+        JwtAlgorithm::EdDsa => Algorithm::EdDSA,
+    }
+}
+
+fn read_private_key(
+    algorithm: JwtAlgorithm,
+    path: &std::path::Path,
+) -> Result<EncodingKey, String> {
+    let pem = std::fs::read(path).map_err(|err| err.to_string())?;
+
+    match algorithm {
+        JwtAlgorithm::Rs256 => EncodingKey::from_rsa_pem(&pem),
+        JwtAlgorithm::EdDsa => EncodingKey::from_ed_pem(&pem),
+    }
+    .map_err(|err| err.to_string())
+}
+
+/// Reads a SubjectPublicKeyInfo PEM into the JWK published for it.
+fn read_public_key(key: &JwtKeyConfig) -> Result<Jwk, String> {
+    let pem = pem::parse(std::fs::read(&key.public_key).map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())?;
+    if pem.tag() != "PUBLIC KEY" {
+        return Err("expected a PEM with BEGIN PUBLIC KEY".to_owned());
+    }
+
+    let invalid = || "not a valid public key for the configured algorithm".to_owned();
+    let blocks = from_der(pem.contents()).map_err(|_| invalid())?;
+    let [ASN1Block::Sequence(_, spki)] = blocks.as_slice() else {
+        return Err(invalid());
+    };
+    let [ASN1Block::Sequence(_, key_algorithm), ASN1Block::BitString(_, _, public_key)] =
+        spki.as_slice()
+    else {
+        return Err(invalid());
+    };
+    let Some(ASN1Block::ObjectIdentifier(_, key_type)) = key_algorithm.first() else {
+        return Err(invalid());
+    };
+
+    let (key_algorithm, parameters) = match key.algorithm {
+        JwtAlgorithm::Rs256 if *key_type == oid!(1, 2, 840, 113549, 1, 1, 1) => {
+            let blocks = from_der(public_key).map_err(|_| invalid())?;
+            let [ASN1Block::Sequence(_, numbers)] = blocks.as_slice() else {
+                return Err(invalid());
+            };
+            let [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] = numbers.as_slice() else {
+                return Err(invalid());
+            };
+            (
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(n.to_bytes_be().1),
+                    e: URL_SAFE_NO_PAD.encode(e.to_bytes_be().1),
+                }),
+            )
+        }
+        JwtAlgorithm::EdDsa if *key_type == oid!(1, 3, 101, 112) => (
+            KeyAlgorithm::EdDSA,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(public_key),
+            }),
+        ),
+        _ => return Err(invalid()),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(key.kid.clone()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+/// Signs and verifies a throwaway token, so a private key that doesn't belong
+/// to its public key is caught at startup rather than at the first request.
+fn check_pair(
+    header: &Header,
+    encoding_key: &EncodingKey,
+    decoding_key: &DecodingKey,
+) -> Result<(), jsonwebtoken::errors::Error> {
+    let claims = serde_json::json!({ "exp": u32::MAX });
+    let token = encode(header, &claims, encoding_key)?;
+    decode::<serde_json::Value>(&token, decoding_key, &Validation::new(header.alg))?;
+    Ok(())
+}
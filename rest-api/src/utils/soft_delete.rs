@@ -0,0 +1,20 @@
+use axum::http::StatusCode;
+
+use super::{app_error::AppError, jwt::Claims};
+use crate::entities::sea_orm_active_enums::Role;
+
+/// Whether a list should include soft-deleted rows. They are left out unless
+/// asked for, and only callers with at least `role` may ask.
+pub fn include_deleted(
+    claims: &Claims,
+    requested: Option<bool>,
+    role: Role,
+) -> Result<bool, AppError> {
+    match requested {
+        Some(true) if claims.role < role => Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            "Insufficient role to list deleted records",
+        )),
+        requested => Ok(requested.unwrap_or_default()),
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use axum::{async_trait, http::StatusCode};
+use tracing::error;
+
+use super::app_error::AppError;
+use crate::config::StorageConfig;
+
+/// Where uploaded files are kept; implement this to move them to e.g. S3.
+///
+/// Keys are relative, `/`-separated paths such as `products/1/ab12.png`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), AppError>;
+
+    /// Removing a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+
+    /// Public URL the file can be fetched from.
+    fn url(&self, key: &str) -> String;
+}
+
+/// Files below a directory on the local disk, served by the app itself
+/// under `base_url`.
+pub struct LocalStorage {
+    root: PathBuf,
+    base_url: String,
+}
+
+impl LocalStorage {
+    pub fn new(config: &StorageConfig) -> Self {
+        Self {
+            root: config.root.clone(),
+            base_url: config.base_url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, AppError> {
+        let relative = Path::new(key);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(storage_error(format!("invalid storage key {}", key)));
+        }
+
+        Ok(self.root.join(relative))
+    }
+}
+
+fn storage_error(message: impl std::fmt::Display) -> AppError {
+    error!("Storage error: {}", message);
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Storage error").with_code("storage_error")
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> Result<(), AppError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(storage_error)?;
+        }
+
+        tokio::fs::write(&path, bytes).await.map_err(storage_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(storage_error(err)),
+            _ => Ok(()),
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url, key)
+    }
+}
@@ -0,0 +1,261 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use rand::{seq::SliceRandom, RngCore};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait,
+    EntityTrait, QueryFilter,
+};
+use totp_rs::{Algorithm, Secret, TOTP};
+use tracing::error;
+
+use super::{
+    app_error::AppError,
+    session::{generate_token, hash_token},
+};
+use crate::{
+    config::TotpConfig,
+    entities::{login_challenge, recovery_code, users},
+};
+
+/// RFC 6238 defaults, which every authenticator app supports.
+const DIGITS: usize = 6;
+const STEP_SECS: u64 = 30;
+
+const RECOVERY_CODES: usize = 10;
+/// Without look-alikes such as `0`/`o` and `1`/`l`.
+const RECOVERY_ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+
+fn totp_error(err: impl std::fmt::Debug) -> AppError {
+    error!("TOTP error: {:?}", err);
+    AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "Error checking code")
+}
+
+/// A fresh 160-bit secret, base32 encoded as authenticator apps expect it.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Secret::Raw(bytes.to_vec()).to_encoded().to_string()
+}
+
+/// The generator for a user's secret. Usernames can't contain `:`, and
+/// `Config::validate` keeps it out of the issuer.
+pub fn totp(secret: &str, config: &TotpConfig, username: &str) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .map_err(totp_error)?;
+
+    // Clock drift is allowed for by `matching_step`, so no skew here.
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        0,
+        STEP_SECS,
+        secret,
+        Some(config.issuer.clone()),
+        username.to_owned(),
+    )
+    .map_err(totp_error)
+}
+
+/// The time step `code` belongs to, if it is one of the current, previous or
+/// next step's codes and newer than `last_step`.
+pub fn matching_step(totp: &TOTP, code: &str, last_step: Option<i64>) -> Option<i64> {
+    let current = Utc::now().timestamp() as u64 / STEP_SECS;
+
+    (current - 1..=current + 1)
+        .filter(|step| last_step.is_none_or(|last| *step as i64 > last))
+        .find(|step| totp.check(code, step * STEP_SECS))
+        .map(|step| step as i64)
+}
+
+/// Accepts a code from the user's authenticator app, or one of their unused
+/// recovery codes. Either works only once.
+pub async fn verify_code<C: ConnectionTrait>(
+    conn: &C,
+    user: &users::Model,
+    config: &TotpConfig,
+    code: &str,
+) -> Result<bool, AppError> {
+    let Some(secret) = &user.totp_secret else {
+        return Ok(false);
+    };
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if code.len() != DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return use_recovery_code(conn, user.id, &code).await;
+    }
+
+    let Some(step) = matching_step(
+        &totp(secret, config, &user.username)?,
+        &code,
+        user.totp_last_step,
+    ) else {
+        return Ok(false);
+    };
+    // Of concurrent logins with the same code only one gets through.
+    let accepted = users::Entity::update_many()
+        .col_expr(users::Column::TotpLastStep, Expr::value(step))
+        .filter(users::Column::Id.eq(user.id))
+        .filter(
+            Condition::any()
+                .add(users::Column::TotpLastStep.is_null())
+                .add(users::Column::TotpLastStep.lt(step)),
+        )
+        .exec(conn)
+        .await?;
+
+    Ok(accepted.rows_affected == 1)
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+/// Replaces the user's recovery codes with new ones and returns them; only
+/// their digests are stored.
+pub async fn replace_recovery_codes<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+) -> Result<Vec<String>, AppError> {
+    recovery_code::Entity::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+
+    let codes: Vec<String> = {
+        let mut rng = rand::thread_rng();
+        (0..RECOVERY_CODES)
+            .map(|_| {
+                let chars: Vec<u8> = (0..10)
+                    .map(|_| *RECOVERY_ALPHABET.choose(&mut rng).unwrap_or(&b'a'))
+                    .collect();
+                format!(
+                    "{}-{}",
+                    String::from_utf8_lossy(&chars[..5]),
+                    String::from_utf8_lossy(&chars[5..])
+                )
+            })
+            .collect()
+    };
+
+    let now = Utc::now().naive_utc();
+    for code in &codes {
+        recovery_code::ActiveModel {
+            id: ActiveValue::NotSet,
+            user_id: ActiveValue::Set(user_id),
+            code_hash: ActiveValue::Set(hash_recovery_code(code)),
+            used_at: ActiveValue::Set(None),
+            created_at: ActiveValue::Set(now),
+        }
+        .insert(conn)
+        .await?;
+    }
+
+    Ok(codes)
+}
+
+async fn use_recovery_code<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    code: &str,
+) -> Result<bool, AppError> {
+    let used = recovery_code::Entity::update_many()
+        .col_expr(
+            recovery_code::Column::UsedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .filter(recovery_code::Column::CodeHash.eq(hash_recovery_code(code)))
+        .filter(recovery_code::Column::UsedAt.is_null())
+        .exec(conn)
+        .await?;
+
+    Ok(used.rows_affected == 1)
+}
+
+pub async fn delete_recovery_codes<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+) -> Result<(), AppError> {
+    recovery_code::Entity::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
+
+fn invalid_challenge() -> AppError {
+    AppError::new(
+        StatusCode::UNAUTHORIZED,
+        "Invalid or expired challenge token",
+    )
+    .with_code("invalid_challenge")
+}
+
+/// Starts the second login step for a user whose password checked out, and
+/// returns the token that has to be presented with a code.
+pub async fn create_challenge<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    config: &TotpConfig,
+) -> Result<String, AppError> {
+    let token = generate_token();
+    let now = Utc::now().naive_utc();
+
+    login_challenge::ActiveModel {
+        id: ActiveValue::NotSet,
+        user_id: ActiveValue::Set(user_id),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        expires_at: ActiveValue::Set(now + Duration::minutes(config.challenge_minutes)),
+        used_at: ActiveValue::Set(None),
+        created_at: ActiveValue::Set(now),
+    }
+    .insert(conn)
+    .await?;
+
+    Ok(token)
+}
+
+/// An unused, unexpired challenge. Wrong codes leave it usable, so a typo
+/// doesn't need the password again; the login lockout limits guessing.
+pub async fn find_challenge<C: ConnectionTrait>(
+    conn: &C,
+    token: &str,
+) -> Result<login_challenge::Model, AppError> {
+    let now = Utc::now().naive_utc();
+
+    login_challenge::Entity::find()
+        .filter(login_challenge::Column::TokenHash.eq(hash_token(token)))
+        .one(conn)
+        .await?
+        .filter(|challenge| challenge.used_at.is_none() && challenge.expires_at > now)
+        .ok_or_else(invalid_challenge)
+}
+
+/// Uses the challenge up; fails if a concurrent request already did.
+pub async fn complete_challenge<C: ConnectionTrait>(
+    conn: &C,
+    challenge: &login_challenge::Model,
+) -> Result<(), AppError> {
+    let used = login_challenge::Entity::update_many()
+        .col_expr(
+            login_challenge::Column::UsedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(login_challenge::Column::Id.eq(challenge.id))
+        .filter(login_challenge::Column::UsedAt.is_null())
+        .exec(conn)
+        .await?;
+
+    if used.rows_affected != 1 {
+        return Err(invalid_challenge());
+    }
+
+    Ok(())
+}
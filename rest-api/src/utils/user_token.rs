@@ -0,0 +1,85 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, EntityTrait,
+    QueryFilter,
+};
+
+use super::{
+    app_error::AppError,
+    session::{generate_token, hash_token},
+};
+use crate::entities::{
+    sea_orm_active_enums::TokenPurpose,
+    user_token::{ActiveModel, Column, Entity, Model},
+};
+
+pub fn invalid_token() -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, "Invalid or expired token").with_code("invalid_token")
+}
+
+/// Creates a token for mailing to `email` and returns it. Tokens issued
+/// earlier for the same purpose stop working, so only the newest mail's
+/// link does.
+pub async fn issue<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    purpose: TokenPurpose,
+    email: &str,
+    ttl: Duration,
+) -> Result<String, AppError> {
+    let now = Utc::now().naive_utc();
+
+    Entity::update_many()
+        .col_expr(Column::UsedAt, Expr::value(now))
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::Purpose.eq(purpose))
+        .filter(Column::UsedAt.is_null())
+        .exec(conn)
+        .await?;
+
+    let token = generate_token();
+    ActiveModel {
+        id: ActiveValue::NotSet,
+        user_id: ActiveValue::Set(user_id),
+        purpose: ActiveValue::Set(purpose),
+        token_hash: ActiveValue::Set(hash_token(&token)),
+        email: ActiveValue::Set(email.to_owned()),
+        expires_at: ActiveValue::Set(now + ttl),
+        used_at: ActiveValue::Set(None),
+        created_at: ActiveValue::Set(now),
+    }
+    .insert(conn)
+    .await?;
+
+    Ok(token)
+}
+
+/// Marks an unused, unexpired token as used and returns it. Of concurrent
+/// attempts to use the same token only one succeeds.
+pub async fn consume<C: ConnectionTrait>(
+    conn: &C,
+    token: &str,
+    purpose: TokenPurpose,
+) -> Result<Model, AppError> {
+    let now = Utc::now().naive_utc();
+    let found = Entity::find()
+        .filter(Column::TokenHash.eq(hash_token(token)))
+        .filter(Column::Purpose.eq(purpose))
+        .one(conn)
+        .await?
+        .filter(|found| found.used_at.is_none() && found.expires_at > now)
+        .ok_or_else(invalid_token)?;
+
+    let used = Entity::update_many()
+        .col_expr(Column::UsedAt, Expr::value(now))
+        .filter(Column::Id.eq(found.id))
+        .filter(Column::UsedAt.is_null())
+        .exec(conn)
+        .await?;
+    if used.rows_affected != 1 {
+        return Err(invalid_token());
+    }
+
+    Ok(found)
+}
@@ -0,0 +1,69 @@
+use validator::ValidationError;
+
+pub const PASSWORD_MIN_LENGTH: usize = 8;
+/// bcrypt only looks at the first 72 bytes of a password.
+pub const PASSWORD_MAX_BYTES: usize = 72;
+
+fn error(code: &'static str, message: &'static str) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        Ok(())
+    } else {
+        Err(error(
+            "username_charset",
+            "may only contain letters, digits, '_', '-' and '.'",
+        ))
+    }
+}
+
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    if password.chars().count() < PASSWORD_MIN_LENGTH {
+        return Err(error(
+            "password_too_short",
+            "must be at least 8 characters long",
+        ));
+    }
+
+    if password.len() > PASSWORD_MAX_BYTES {
+        return Err(error("password_too_long", "must be at most 72 bytes long"));
+    }
+
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+
+    if !has_letter || !has_digit {
+        return Err(error(
+            "password_too_weak",
+            "must contain at least one letter and one digit",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Lowercase ASCII words joined by hyphens, e.g. "Board Games" -> "board-games".
+/// Empty when `name` has no ASCII letters or digits.
+pub fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+pub fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    if slugify(slug) == slug {
+        Ok(())
+    } else {
+        Err(error(
+            "slug_format",
+            "may only contain lowercase letters and digits separated by single hyphens",
+        ))
+    }
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{spawn_app, TestApp, PASSWORD};
+
+async fn signup_with_email(app: &TestApp, username: &str, email: &str) -> Value {
+    app.server
+        .post("/auth/signup")
+        .json(&json!({ "username": username, "password": PASSWORD, "email": email }))
+        .await
+        .json()
+}
+
+async fn verify(app: &TestApp, token: &str) -> axum_test::TestResponse {
+    app.server
+        .post("/auth/verify-email")
+        .json(&json!({ "token": token }))
+        .await
+}
+
+async fn users(app: &TestApp, token: &str) -> Value {
+    app.server
+        .get("/users")
+        .authorization_bearer(token)
+        .await
+        .json::<Value>()["items"][0]
+        .clone()
+}
+
+#[tokio::test]
+async fn signup_mails_a_single_use_verification_link() {
+    let app = spawn_app().await;
+
+    let user = signup_with_email(&app, "alice", "Alice@Example.com").await;
+    assert_eq!(user["email"], "alice@example.com");
+    assert_eq!(user["email_verified_at"], Value::Null);
+
+    let token = app.mailed_token("alice@example.com").await;
+    verify(&app, &token).await.assert_status_ok();
+    let access_token = app.login("alice").await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    assert!(users(&app, &access_token).await["email_verified_at"].is_string());
+
+    let response = verify(&app, &token).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(response.json::<Value>()["code"], "invalid_token");
+
+    // Nothing left to verify.
+    let response = app
+        .server
+        .post("/users/verification")
+        .authorization_bearer(&access_token)
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    assert_eq!(response.json::<Value>()["code"], "email_already_verified");
+}
+
+#[tokio::test]
+async fn only_the_latest_link_for_the_current_address_verifies() {
+    let app = spawn_app().await;
+    let id = signup_with_email(&app, "alice", "alice@example.com").await["id"].clone();
+    let first = app.mailed_token("alice@example.com").await;
+    let access_token = app.login("alice").await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    app.server
+        .post("/users/verification")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+    let second = app.mailed_token("alice@example.com").await;
+    assert_ne!(first, second);
+    verify(&app, &first)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Changing the address voids the link sent to the old one.
+    let updated: Value = app
+        .server
+        .put("/users")
+        .authorization_bearer(&access_token)
+        .json(&json!({ "id": id, "email": "alice@example.org" }))
+        .await
+        .json();
+    assert_eq!(updated["email"], "alice@example.org");
+    verify(&app, &second)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let token = app.mailed_token("alice@example.org").await;
+    verify(&app, &token).await.assert_status_ok();
+    assert!(users(&app, &access_token).await["email_verified_at"].is_string());
+
+    // Addresses are unique.
+    let response = app
+        .server
+        .post("/auth/signup")
+        .json(&json!({ "username": "bob", "password": PASSWORD, "email": "ALICE@example.org" }))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn password_reset_changes_the_password_and_revokes_sessions() {
+    let app = spawn_app().await;
+    signup_with_email(&app, "alice", "alice@example.com").await;
+    let token = app.mailed_token("alice@example.com").await;
+
+    // Unverified addresses get no reset link.
+    let forgot = |email: &'static str| {
+        app.server
+            .post("/auth/forgot-password")
+            .json(&json!({ "email": email }))
+    };
+    forgot("alice@example.com").await.assert_status_ok();
+    assert_eq!(app.mails_to("alice@example.com").await.len(), 1);
+
+    verify(&app, &token).await.assert_status_ok();
+    let refresh_token = app.login("alice").await["refresh_token"].clone();
+
+    let unknown: Value = forgot("nobody@example.com").await.json();
+    let known: Value = forgot("Alice@example.com").await.json();
+    assert_eq!(unknown, known);
+    let reset = app.mailed_token("alice@example.com").await;
+    assert_ne!(reset, token);
+
+    let response = app
+        .server
+        .post("/auth/reset-password")
+        .json(&json!({ "token": reset, "password": "short" }))
+        .await;
+    response.assert_status_unprocessable_entity();
+
+    app.server
+        .post("/auth/reset-password")
+        .json(&json!({ "token": reset, "password": "new-password1" }))
+        .await
+        .assert_status_ok();
+
+    app.server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": PASSWORD }))
+        .await
+        .assert_status_unauthorized();
+    app.server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": "new-password1" }))
+        .await
+        .assert_status_ok();
+    app.server
+        .post("/auth/refresh")
+        .json(&json!({ "refresh_token": refresh_token }))
+        .await
+        .assert_status_unauthorized();
+
+    let response = app
+        .server
+        .post("/auth/reset-password")
+        .json(&json!({ "token": reset, "password": "other-password1" }))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(response.json::<Value>()["code"], "invalid_token");
+}
+
+#[tokio::test]
+async fn reset_links_to_a_previous_address_are_void() {
+    let app = spawn_app().await;
+    let id = signup_with_email(&app, "alice", "alice@example.com").await["id"].clone();
+    let token = app.mailed_token("alice@example.com").await;
+    verify(&app, &token).await.assert_status_ok();
+    let access_token = app.login("alice").await["access_token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    app.server
+        .post("/auth/forgot-password")
+        .json(&json!({ "email": "alice@example.com" }))
+        .await
+        .assert_status_ok();
+    let reset = app.mailed_token("alice@example.com").await;
+
+    app.server
+        .put("/users")
+        .authorization_bearer(&access_token)
+        .json(&json!({ "id": id, "email": "alice@example.org" }))
+        .await
+        .assert_status_ok();
+
+    let response = app
+        .server
+        .post("/auth/reset-password")
+        .json(&json!({ "token": reset, "password": "new-password1" }))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(response.json::<Value>()["code"], "invalid_token");
+}
+
+#[tokio::test]
+async fn made_up_reset_tokens_are_rejected() {
+    let app = spawn_app().await;
+    signup_with_email(&app, "alice", "alice@example.com").await;
+
+    let response = app
+        .server
+        .post("/auth/reset-password")
+        .json(&json!({ "token": "0".repeat(64), "password": "new-password1" }))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(response.json::<Value>()["code"], "invalid_token");
+    app.login("alice").await["access_token"]
+        .as_str()
+        .expect("the password is unchanged");
+}
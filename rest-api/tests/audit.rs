@@ -0,0 +1,192 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_project::entities::sea_orm_active_enums::Role;
+use serde_json::{json, Value};
+
+use common::spawn_app;
+
+#[tokio::test]
+async fn deleted_products_are_hidden_restorable_and_audited() {
+    let app = spawn_app().await;
+    let (editor, token) = app.user_with_role("editor", Role::Editor).await;
+    let (_, user_token) = app.user_with_role("alice", Role::User).await;
+    let (_, admin_token) = app.user_with_role("admin", Role::Admin).await;
+
+    app.server
+        .post("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "name": "books" }))
+        .await
+        .assert_status_ok();
+    let product: Value = app
+        .server
+        .post("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "title": "Dune", "price": 10, "category": "books" }))
+        .await
+        .json();
+    let id = product["id"].as_i64().unwrap();
+    app.server
+        .put("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": id, "price": 12 }))
+        .await
+        .assert_status_ok();
+    app.server
+        .delete("/product")
+        .authorization_bearer(&token)
+        .add_query_param("id", id)
+        .await
+        .assert_status_ok();
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(page["total"], 0);
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .add_query_param("include_deleted", true)
+        .await
+        .json();
+    assert_eq!(page["total"], 1);
+    assert!(page["items"][0]["deleted_at"].is_string());
+    app.server
+        .get("/product")
+        .authorization_bearer(&user_token)
+        .add_query_param("include_deleted", true)
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // Deleted products can't be edited, and a category can go once only
+    // deleted products are left in it.
+    app.server
+        .put("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": id, "price": 1 }))
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.server
+        .delete("/category")
+        .authorization_bearer(&token)
+        .add_query_param("slug", "books")
+        .await
+        .assert_status_ok();
+    let response = app
+        .server
+        .post("/product/restore")
+        .authorization_bearer(&token)
+        .add_query_param("id", id)
+        .expect_failure()
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    assert_eq!(response.json::<Value>()["code"], "category_deleted");
+
+    app.server
+        .post("/category/restore")
+        .authorization_bearer(&token)
+        .add_query_param("id", product["category_id"].as_i64().unwrap())
+        .await
+        .assert_status_ok();
+    let restored: Value = app
+        .server
+        .post("/product/restore")
+        .authorization_bearer(&token)
+        .add_query_param("id", id)
+        .await
+        .json();
+    assert_eq!(restored["price"], 12);
+    assert_eq!(restored["deleted_at"], Value::Null);
+    app.server
+        .post("/product/restore")
+        .authorization_bearer(&token)
+        .add_query_param("id", id)
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let log: Value = app
+        .server
+        .get("/audit")
+        .authorization_bearer(&admin_token)
+        .add_query_param("entity", "product")
+        .add_query_param("entity_id", id)
+        .add_query_param("sort", "id")
+        .await
+        .json();
+    let actions: Vec<&str> = log["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["create", "update", "delete", "restore"]);
+    let update = &log["items"][1];
+    assert_eq!(update["actor_id"], editor);
+    assert_eq!(update["actor"], "editor");
+    assert_eq!(update["before"]["price"], 10);
+    assert_eq!(update["after"]["price"], 12);
+
+    app.server
+        .get("/audit")
+        .authorization_bearer(&token)
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn deleted_users_cannot_log_in_until_restored() {
+    let app = spawn_app().await;
+    let (alice, token) = app.user_with_role("alice", Role::User).await;
+    let (_, admin_token) = app.user_with_role("admin", Role::Admin).await;
+
+    app.server
+        .delete("/users")
+        .authorization_bearer(&token)
+        .add_query_param("id", alice)
+        .await
+        .assert_status_ok();
+    app.server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": common::PASSWORD }))
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let page: Value = app
+        .server
+        .get("/users")
+        .authorization_bearer(&admin_token)
+        .await
+        .json();
+    assert_eq!(page["total"], 1);
+
+    app.server
+        .post("/users/restore")
+        .authorization_bearer(&admin_token)
+        .add_query_param("id", alice)
+        .await
+        .assert_status_ok();
+    assert!(app.login("alice").await["access_token"].is_string());
+
+    let log: Value = app
+        .server
+        .get("/audit")
+        .authorization_bearer(&admin_token)
+        .add_query_param("entity", "user")
+        .add_query_param("entity_id", alice)
+        .await
+        .json();
+    assert_eq!(log["items"][0]["action"], "restore");
+    assert_eq!(log["items"][1]["action"], "delete");
+    assert_eq!(log["items"][1]["actor_id"], alice);
+    assert!(log["items"][1]["before"].get("password").is_none());
+}
@@ -0,0 +1,278 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use axum_project::{
+    config::PasswordAlgorithm,
+    entities::{
+        sea_orm_active_enums::Role,
+        users::{ActiveModel as UserActiveModel, Entity as Users},
+    },
+};
+use common::{spawn_app, spawn_app_with, test_config, PASSWORD};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+
+#[tokio::test]
+async fn signup_creates_user_without_exposing_plain_password() {
+    let app = spawn_app().await;
+
+    let response = app
+        .server
+        .post("/auth/signup")
+        .json(&json!({ "username": "alice", "password": PASSWORD }))
+        .await;
+
+    response.assert_status_ok();
+    let user: Value = response.json();
+    assert_eq!(user["username"], "alice");
+    assert_eq!(user["role"], "user");
+    assert_ne!(user["password"], PASSWORD);
+}
+
+#[tokio::test]
+async fn signup_rejects_invalid_payload_with_every_field() {
+    let app = spawn_app().await;
+
+    let response = app
+        .server
+        .post("/auth/signup")
+        .json(&json!({ "username": "a b", "password": "short" }))
+        .await;
+
+    response.assert_status_unprocessable_entity();
+    let body: Value = response.json();
+    assert_eq!(body["code"], "validation_failed");
+    assert!(body["details"]["fields"]["username"].is_array());
+    assert!(body["details"]["fields"]["password"].is_array());
+    assert!(body["request_id"].is_string());
+}
+
+#[tokio::test]
+async fn login_returns_token_pair() {
+    let app = spawn_app().await;
+    app.signup("alice").await;
+
+    let tokens = app.login("alice").await;
+
+    assert!(tokens["access_token"].is_string());
+    assert!(tokens["refresh_token"].is_string());
+    assert_eq!(tokens["token_type"], "Bearer");
+}
+
+#[tokio::test]
+async fn login_rejects_wrong_password() {
+    let app = spawn_app().await;
+    app.signup("alice").await;
+
+    let response = app
+        .server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": "wrong-password1" }))
+        .await;
+
+    response.assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn login_does_not_reveal_unknown_users() {
+    let app = spawn_app().await;
+    app.signup("alice").await;
+
+    let wrong_password = app
+        .server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": "wrong-password1" }))
+        .await;
+    let unknown_user = app
+        .server
+        .post("/auth/login")
+        .json(&json!({ "username": "nobody", "password": "wrong-password1" }))
+        .await;
+
+    unknown_user.assert_status_unauthorized();
+    let (wrong_password, unknown_user) =
+        (wrong_password.json::<Value>(), unknown_user.json::<Value>());
+    assert_eq!(wrong_password["code"], unknown_user["code"]);
+    assert_eq!(wrong_password["message"], unknown_user["message"]);
+}
+
+async fn password_hash(app: &common::TestApp, id: i32) -> String {
+    Users::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .unwrap()
+        .unwrap()
+        .password
+}
+
+#[tokio::test]
+async fn login_rehashes_bcrypt_passwords_with_argon2id() {
+    let app = spawn_app().await;
+    let id = app.signup("alice").await["id"].as_i64().unwrap() as i32;
+    assert!(password_hash(&app, id).await.starts_with("$argon2id$"));
+
+    let mut user: UserActiveModel = Users::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .unwrap()
+        .unwrap()
+        .into();
+    user.password = ActiveValue::Set(bcrypt::hash(PASSWORD, 4).unwrap());
+    user.update(&app.conn).await.unwrap();
+
+    assert!(app.login("alice").await["access_token"].is_string());
+    let rehashed = password_hash(&app, id).await;
+    assert!(rehashed.starts_with("$argon2id$v=19$m=8,t=1,p=1$"));
+
+    // The new hash verifies, and isn't replaced again.
+    assert!(app.login("alice").await["access_token"].is_string());
+    assert_eq!(password_hash(&app, id).await, rehashed);
+}
+
+#[tokio::test]
+async fn bcrypt_hasher_rehashes_on_cost_change() {
+    let mut config = test_config();
+    config.auth.password_hasher = PasswordAlgorithm::Bcrypt;
+    config.auth.bcrypt_cost = 5;
+    let app = spawn_app_with(config).await;
+    let id = app.signup("alice").await["id"].as_i64().unwrap() as i32;
+    assert!(password_hash(&app, id).await.starts_with("$2b$05$"));
+
+    let mut user: UserActiveModel = Users::find_by_id(id)
+        .one(&app.conn)
+        .await
+        .unwrap()
+        .unwrap()
+        .into();
+    user.password = ActiveValue::Set(bcrypt::hash(PASSWORD, 4).unwrap());
+    user.update(&app.conn).await.unwrap();
+
+    assert!(app.login("alice").await["access_token"].is_string());
+    assert!(password_hash(&app, id).await.starts_with("$2b$05$"));
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_out_username_until_admin_unlocks() {
+    let app = spawn_app().await;
+    app.signup("alice").await;
+    let (_, admin_token) = app.user_with_role("root", Role::Admin).await;
+
+    for _ in 0..5 {
+        app.server
+            .post("/auth/login")
+            .json(&json!({ "username": "alice", "password": "wrong-password1" }))
+            .await
+            .assert_status_unauthorized();
+    }
+
+    let response = app
+        .server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": PASSWORD }))
+        .await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.json::<Value>()["code"], "login_locked");
+    assert!(response.headers().contains_key("retry-after"));
+
+    app.server
+        .post("/users/unlock")
+        .authorization_bearer(&admin_token)
+        .json(&json!({ "username": "alice" }))
+        .await
+        .assert_status_ok();
+
+    assert!(app.login("alice").await["access_token"].is_string());
+}
+
+#[tokio::test]
+async fn unlock_requires_admin() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("alice", Role::Editor).await;
+
+    app.server
+        .post("/users/unlock")
+        .authorization_bearer(&token)
+        .json(&json!({ "username": "alice" }))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+async fn failures_across_usernames_lock_out_client_ip() {
+    let mut config = test_config();
+    config.auth.lockout.max_attempts_per_ip = 3;
+    let app = spawn_app_with(config).await;
+    app.signup("alice").await;
+
+    for username in ["bob", "carol", "dave"] {
+        app.server
+            .post("/auth/login")
+            .json(&json!({ "username": username, "password": "wrong-password1" }))
+            .await
+            .assert_status_unauthorized();
+    }
+
+    app.server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": PASSWORD }))
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn refresh_rotates_token_and_detects_reuse() {
+    let app = spawn_app().await;
+    app.signup("alice").await;
+    let refresh_token = app.login("alice").await["refresh_token"].clone();
+
+    let response = app
+        .server
+        .post("/auth/refresh")
+        .json(&json!({ "refresh_token": refresh_token }))
+        .await;
+    response.assert_status_ok();
+    let rotated = response.json::<Value>()["refresh_token"].clone();
+    assert_ne!(rotated, refresh_token);
+
+    // Replaying the old token revokes the whole family.
+    app.server
+        .post("/auth/refresh")
+        .json(&json!({ "refresh_token": refresh_token }))
+        .await
+        .assert_status_unauthorized();
+    app.server
+        .post("/auth/refresh")
+        .json(&json!({ "refresh_token": rotated }))
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn logout_revokes_refresh_token() {
+    let app = spawn_app().await;
+    app.signup("alice").await;
+    let refresh_token = app.login("alice").await["refresh_token"].clone();
+
+    app.server
+        .post("/auth/logout")
+        .json(&json!({ "refresh_token": refresh_token }))
+        .await
+        .assert_status_ok();
+
+    app.server
+        .post("/auth/refresh")
+        .json(&json!({ "refresh_token": refresh_token }))
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn protected_routes_require_token() {
+    let app = spawn_app().await;
+
+    let response = app.server.get("/users").await;
+
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json::<Value>()["code"], "unauthorized");
+}
@@ -0,0 +1,254 @@
+mod common;
+
+use axum_project::entities::sea_orm_active_enums::Role;
+use serde_json::{json, Value};
+
+use common::{spawn_app, TestApp};
+
+#[tokio::test]
+async fn editor_manages_categories() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    app.server
+        .post("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "name": "books" }))
+        .await
+        .assert_status_ok();
+
+    let page: Value = app
+        .server
+        .get("/category")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(
+        page["items"],
+        json!([{ "id": 1, "name": "books", "slug": "books", "parent_id": null, "deleted_at": null }])
+    );
+
+    app.server
+        .delete("/category")
+        .authorization_bearer(&token)
+        .add_query_param("name", "books")
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn duplicate_category_is_conflict() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    app.server
+        .post("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "name": "books" }))
+        .await
+        .assert_status_ok();
+
+    let response = app
+        .server
+        .post("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "name": "books" }))
+        .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+    assert_eq!(response.json::<Value>()["code"], "conflict");
+}
+
+#[tokio::test]
+async fn plain_user_cannot_create_category() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("alice", Role::User).await;
+
+    app.server
+        .post("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "name": "books" }))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+async fn deleting_missing_category_is_not_found() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    app.server
+        .delete("/category")
+        .authorization_bearer(&token)
+        .add_query_param("name", "nope")
+        .await
+        .assert_status_not_found();
+}
+
+async fn create(app: &TestApp, token: &str, body: Value) -> Value {
+    let response = app
+        .server
+        .post("/category")
+        .authorization_bearer(token)
+        .json(&body)
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn tree_nests_categories_and_counts_products() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    let media = create(&app, &token, json!({ "name": "Media" })).await;
+    let books = create(
+        &app,
+        &token,
+        json!({ "name": "Books", "parent_id": media["id"] }),
+    )
+    .await;
+    assert_eq!(books["slug"], "books");
+    create(
+        &app,
+        &token,
+        json!({ "name": "Board Games", "parent_id": media["id"] }),
+    )
+    .await;
+
+    for category in ["media", "books", "books"] {
+        app.server
+            .post("/product")
+            .authorization_bearer(&token)
+            .json(&json!({ "title": "Item", "price": 1, "category": category }))
+            .await
+            .assert_status_ok();
+    }
+
+    let tree: Value = app
+        .server
+        .get("/category/tree")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(tree[0]["slug"], "media");
+    assert_eq!(tree[0]["product_count"], 1);
+    assert_eq!(tree[0]["total_product_count"], 3);
+    assert_eq!(tree[0]["children"][0]["slug"], "board-games");
+    assert_eq!(tree[0]["children"][1]["product_count"], 2);
+
+    // Filtering by a parent includes its subcategories.
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .add_query_param("category", "media")
+        .await
+        .json();
+    assert_eq!(page["total"], 3);
+}
+
+#[tokio::test]
+async fn rename_keeps_products_and_rejects_cycles() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    let parent = create(&app, &token, json!({ "name": "books" })).await;
+    let child = create(
+        &app,
+        &token,
+        json!({ "name": "novels", "parent_id": parent["id"] }),
+    )
+    .await;
+    app.server
+        .post("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "title": "Dune", "price": 9, "category": "books" }))
+        .await
+        .assert_status_ok();
+
+    let renamed: Value = app
+        .server
+        .put("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": parent["id"], "name": "Printed books" }))
+        .await
+        .json();
+    assert_eq!(renamed["name"], "Printed books");
+    assert_eq!(renamed["slug"], "books");
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .add_query_param("category", "books")
+        .await
+        .json();
+    assert_eq!(page["items"][0]["category_id"], parent["id"]);
+
+    let response = app
+        .server
+        .put("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": parent["id"], "parent_id": child["id"] }))
+        .await;
+    response.assert_status_unprocessable_entity();
+    assert!(response.json::<Value>()["details"]["fields"]["parent_id"].is_array());
+
+    let moved: Value = app
+        .server
+        .put("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": child["id"], "parent_id": null }))
+        .await
+        .json();
+    assert_eq!(moved["parent_id"], Value::Null);
+}
+
+#[tokio::test]
+async fn delete_matches_exactly_and_rejects_or_reassigns_products() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    create(&app, &token, json!({ "name": "books" })).await;
+    create(&app, &token, json!({ "name": "ebooks" })).await;
+    app.server
+        .post("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "title": "Dune", "price": 9, "category": "ebooks" }))
+        .await
+        .assert_status_ok();
+
+    let response = app
+        .server
+        .delete("/category")
+        .authorization_bearer(&token)
+        .add_query_param("slug", "ebooks")
+        .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+    assert_eq!(response.json::<Value>()["code"], "category_in_use");
+
+    app.server
+        .delete("/category")
+        .authorization_bearer(&token)
+        .add_query_param("slug", "ebooks")
+        .add_query_param("reassign_to", "books")
+        .await
+        .assert_status_ok();
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .add_query_param("category", "books")
+        .await
+        .json();
+    assert_eq!(page["items"][0]["title"], "Dune");
+
+    // "book" is a substring of "books" but not a category name.
+    app.server
+        .delete("/category")
+        .authorization_bearer(&token)
+        .add_query_param("name", "book")
+        .await
+        .assert_status_not_found();
+}
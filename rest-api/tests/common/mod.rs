@@ -0,0 +1,173 @@
+#![allow(dead_code)]
+
+use axum_project::{
+    app::create_app,
+    config::{Config, MailTransport},
+    entities::{
+        sea_orm_active_enums::Role,
+        users::{ActiveModel as UserActiveModel, Entity as Users},
+    },
+};
+use std::{collections::HashSet, net::SocketAddr, path::PathBuf, sync::Mutex, time::Duration};
+
+use axum_test::TestServer;
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ActiveModelTrait, ActiveValue, Database, DatabaseConnection, EntityTrait};
+use serde_json::{json, Value};
+
+pub const PASSWORD: &str = "password123";
+
+pub struct TestApp {
+    pub server: TestServer,
+    pub conn: DatabaseConnection,
+    /// Where mails are written to, one `.eml` file each.
+    pub mail_dir: PathBuf,
+    /// Tokens `mailed_token` has returned.
+    seen_tokens: Mutex<HashSet<String>>,
+}
+
+/// Starts the app against a fresh in-memory SQLite database with every
+/// migration applied.
+pub async fn spawn_app() -> TestApp {
+    spawn_app_with(test_config()).await
+}
+
+/// Defaults with a fixed secret, the cheapest password hashing parameters, no
+/// login backoff, no rate limiting, and uploads and mails stored in a fresh
+/// temporary directory.
+pub fn test_config() -> Config {
+    let mut config = Config::default();
+    config.database.url = "sqlite::memory:".to_owned();
+    config.auth.secret_key = "test-secret".to_owned();
+    config.auth.bcrypt_cost = 4;
+    config.auth.argon2.memory_kib = 8;
+    config.auth.argon2.iterations = 1;
+    config.auth.lockout.backoff_base_ms = 0;
+    config.rate_limit.enabled = false;
+    config.storage.root = std::env::temp_dir().join(format!(
+        "axum-project-test-{}",
+        hex::encode(rand::random::<[u8; 8]>())
+    ));
+    config.mail.transport = MailTransport::File;
+    config.mail.dir = config.storage.root.join("mail");
+    config
+}
+
+pub async fn spawn_app_with(config: Config) -> TestApp {
+    let conn = Database::connect(&config.database.url)
+        .await
+        .expect("failed to open in-memory database");
+    Migrator::up(&conn, None)
+        .await
+        .expect("failed to run migrations");
+
+    let mail_dir = config.mail.dir.clone();
+    // Served over a real socket so handlers see the client's address.
+    let app = create_app(conn.clone(), config).into_make_service_with_connect_info::<SocketAddr>();
+    let server = TestServer::new(app).expect("failed to start test server");
+
+    TestApp {
+        server,
+        conn,
+        mail_dir,
+        seen_tokens: Mutex::default(),
+    }
+}
+
+/// The token in a mail's link, if it has been written completely.
+fn link_token(mail: &str) -> Option<String> {
+    // Undoes the quoted-printable encoding long lines get.
+    let mail = mail.replace("=\r\n", "").replace("=3D", "=");
+    let start = mail.find("?token=")? + "?token=".len();
+    let token: String = mail[start..]
+        .chars()
+        .take_while(char::is_ascii_hexdigit)
+        .collect();
+    (!token.is_empty()).then_some(token)
+}
+
+impl TestApp {
+    /// The mails sent to `address` so far, oldest first. Waits briefly for
+    /// at least one, as most are sent in the background.
+    pub async fn mails_to(&self, address: &str) -> Vec<String> {
+        let recipient = format!("To: {}", address);
+
+        for _ in 0..50 {
+            let mut names: Vec<_> = std::fs::read_dir(&self.mail_dir)
+                .map(|entries| entries.filter_map(Result::ok).map(|e| e.path()).collect())
+                .unwrap_or_default();
+            names.sort();
+            let mails: Vec<String> = names
+                .iter()
+                .filter_map(|path| std::fs::read_to_string(path).ok())
+                .filter(|mail| mail.lines().any(|line| line == recipient))
+                .collect();
+            if !mails.is_empty() {
+                return mails;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        Vec::new()
+    }
+
+    /// The token in the link of the latest mail to `address`. Waits for a
+    /// token not returned before, so a mail still being sent isn't mistaken
+    /// for an earlier one.
+    pub async fn mailed_token(&self, address: &str) -> String {
+        for _ in 0..50 {
+            if let Some(token) = self
+                .mails_to(address)
+                .await
+                .last()
+                .and_then(|mail| link_token(mail))
+            {
+                if self.seen_tokens.lock().unwrap().insert(token.clone()) {
+                    return token;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("no new mail was sent to {}", address);
+    }
+
+    pub async fn signup(&self, username: &str) -> Value {
+        self.server
+            .post("/auth/signup")
+            .json(&json!({ "username": username, "password": PASSWORD }))
+            .await
+            .json()
+    }
+
+    pub async fn login(&self, username: &str) -> Value {
+        self.server
+            .post("/auth/login")
+            .json(&json!({ "username": username, "password": PASSWORD }))
+            .await
+            .json()
+    }
+
+    /// Signs a user up with the given role and returns its id and access token.
+    pub async fn user_with_role(&self, username: &str, role: Role) -> (i32, String) {
+        let id = self.signup(username).await["id"].as_i64().unwrap() as i32;
+
+        if role != Role::User {
+            let mut user: UserActiveModel = Users::find_by_id(id)
+                .one(&self.conn)
+                .await
+                .unwrap()
+                .unwrap()
+                .into();
+            user.role = ActiveValue::Set(role);
+            user.update(&self.conn).await.unwrap();
+        }
+
+        let token = self.login(username).await["access_token"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+
+        (id, token)
+    }
+}
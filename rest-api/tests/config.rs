@@ -0,0 +1,39 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{spawn_app_with, test_config, PASSWORD};
+use serde_json::json;
+
+#[test]
+fn validate_reports_every_problem() {
+    let mut config = test_config();
+    config.server.bind_address = "localhost".to_owned();
+    config.auth.secret_key.clear();
+    config.auth.bcrypt_cost = 2;
+    config.auth.argon2.parallelism = 0;
+
+    let message = config.validate().unwrap_err().to_string();
+
+    assert!(message.contains("server.bind_address"));
+    assert!(message.contains("auth.secret_key"));
+    assert!(message.contains("auth.bcrypt_cost"));
+    assert!(message.contains("auth.argon2"));
+    assert!(test_config().validate().is_ok());
+}
+
+#[tokio::test]
+async fn token_lifetime_follows_config() {
+    let mut config = test_config();
+    config.auth.access_token_minutes = 5;
+    let app = spawn_app_with(config).await;
+
+    app.signup("alice").await;
+    let response = app
+        .server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": PASSWORD }))
+        .await;
+
+    response.assert_status(StatusCode::OK);
+    assert_eq!(response.json::<serde_json::Value>()["expires_in"], 300);
+}
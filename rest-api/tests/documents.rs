@@ -0,0 +1,98 @@
+mod common;
+
+use axum::http::{header, HeaderValue, StatusCode};
+use serde_json::Value;
+
+use common::{spawn_app, spawn_app_with, test_config};
+
+#[tokio::test]
+async fn text_serves_alice_with_validators() {
+    let app = spawn_app().await;
+
+    let response = app.server.get("/text").await;
+    response.assert_status_ok();
+    assert!(response.text().contains("Alice"));
+    assert_eq!(
+        response.header(header::CONTENT_TYPE),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(response.header(header::ACCEPT_RANGES), "bytes");
+    let etag = response.header(header::ETAG);
+    let last_modified = response.header(header::LAST_MODIFIED);
+
+    app.server
+        .get("/text")
+        .add_header(header::IF_NONE_MATCH, etag.clone())
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+    app.server
+        .get("/text")
+        .add_header(header::IF_MODIFIED_SINCE, last_modified)
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+    app.server
+        .get("/text")
+        .add_header(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn documents_support_byte_ranges() {
+    let mut config = test_config();
+    config.documents.root = config.storage.root.join("documents");
+    std::fs::create_dir_all(config.documents.root.join("notes")).unwrap();
+    std::fs::write(config.documents.root.join("notes/digits.txt"), "0123456789").unwrap();
+    let app = spawn_app_with(config).await;
+
+    let response = app
+        .server
+        .get("/documents/notes/digits.txt")
+        .add_header(header::RANGE, HeaderValue::from_static("bytes=2-4"))
+        .await;
+    response.assert_status(StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.text(), "234");
+    assert_eq!(response.header(header::CONTENT_RANGE), "bytes 2-4/10");
+    let etag = response.header(header::ETAG);
+
+    let response = app
+        .server
+        .get("/documents/notes/digits.txt")
+        .add_header(header::RANGE, HeaderValue::from_static("bytes=-3"))
+        .add_header(header::IF_RANGE, etag)
+        .await;
+    response.assert_status(StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.text(), "789");
+
+    // A stale If-Range gets the whole document instead.
+    let response = app
+        .server
+        .get("/documents/notes/digits.txt")
+        .add_header(header::RANGE, HeaderValue::from_static("bytes=8-"))
+        .add_header(header::IF_RANGE, HeaderValue::from_static("\"stale\""))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.text(), "0123456789");
+
+    let response = app
+        .server
+        .get("/documents/notes/digits.txt")
+        .add_header(header::RANGE, HeaderValue::from_static("bytes=10-"))
+        .expect_failure()
+        .await;
+    response.assert_status(StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.header(header::CONTENT_RANGE), "bytes */10");
+    assert_eq!(response.json::<Value>()["code"], "range_not_satisfiable");
+}
+
+#[tokio::test]
+async fn missing_documents_are_not_found() {
+    let app = spawn_app().await;
+
+    // Clients resolve a literal `..` segment themselves; this one reaches us.
+    for path in ["/documents/missing.txt", "/documents/..%2FCargo.toml"] {
+        let response = app.server.get(path).expect_failure().await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(response.json::<Value>()["code"], "not_found");
+    }
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::spawn_app;
+use serde_json::Value;
+
+#[tokio::test]
+async fn healthz_reports_ok() {
+    let app = spawn_app().await;
+
+    let response = app.server.get("/healthz").await;
+
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["status"], "ok");
+}
+
+#[tokio::test]
+async fn readyz_pings_the_database() {
+    let app = spawn_app().await;
+
+    app.server.get("/readyz").await.assert_status_ok();
+
+    app.conn.clone().close().await.unwrap();
+
+    let response = app.server.get("/readyz").await;
+    response.assert_status_service_unavailable();
+    assert_eq!(response.json::<Value>()["code"], "service_unavailable");
+}
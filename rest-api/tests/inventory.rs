@@ -0,0 +1,165 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_project::entities::sea_orm_active_enums::Role;
+use futures::future::join_all;
+use serde_json::{json, Value};
+use std::future::IntoFuture;
+
+use common::{spawn_app, TestApp};
+
+async fn product(app: &TestApp, token: &str) -> i64 {
+    app.server
+        .post("/category")
+        .authorization_bearer(token)
+        .json(&json!({ "name": "books" }))
+        .await
+        .assert_status_ok();
+
+    let product: Value = app
+        .server
+        .post("/product")
+        .authorization_bearer(token)
+        .json(&json!({ "title": "Rust in Action", "price": 40, "category": "books" }))
+        .await
+        .json();
+    assert_eq!(product["stock"], 0);
+
+    product["id"].as_i64().unwrap()
+}
+
+async fn adjust(app: &TestApp, token: &str, product_id: i64, quantity: i32, reason: &str) -> Value {
+    app.server
+        .post("/product/stock")
+        .authorization_bearer(token)
+        .json(&json!({ "product_id": product_id, "quantity": quantity, "reason": reason }))
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn adjustments_are_recorded_in_the_ledger() {
+    let app = spawn_app().await;
+    let (editor_id, token) = app.user_with_role("editor", Role::Editor).await;
+    let product_id = product(&app, &token).await;
+
+    let movement = adjust(&app, &token, product_id, 10, "restock").await;
+    assert_eq!(movement["stock_after"], 10);
+    assert_eq!(movement["user_id"], editor_id);
+    adjust(&app, &token, product_id, -7, "sale").await;
+
+    let ledger: Value = app
+        .server
+        .get("/product/stock")
+        .authorization_bearer(&token)
+        .add_query_param("product_id", product_id)
+        .await
+        .json();
+    assert_eq!(ledger["total"], 2);
+    assert_eq!(ledger["items"][0]["reason"], "sale");
+    assert_eq!(ledger["items"][0]["quantity"], -7);
+    assert_eq!(ledger["items"][0]["stock_after"], 3);
+
+    let low: Value = app
+        .server
+        .get("/product/low-stock")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(low["total"], 1);
+    assert_eq!(low["items"][0]["stock"], 3);
+
+    let low: Value = app
+        .server
+        .get("/product/low-stock")
+        .authorization_bearer(&token)
+        .add_query_param("threshold", 2)
+        .await
+        .json();
+    assert_eq!(low["total"], 0);
+}
+
+#[tokio::test]
+async fn stock_never_goes_negative() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    let product_id = product(&app, &token).await;
+    adjust(&app, &token, product_id, 5, "restock").await;
+
+    let body = adjust(&app, &token, product_id, -6, "correction").await;
+    assert_eq!(body["code"], "insufficient_stock");
+    assert_eq!(body["details"]["stock"], 5);
+
+    let body = adjust(&app, &token, product_id, 3, "sale").await;
+    assert_eq!(body["code"], "validation_failed");
+    assert!(body["details"]["fields"]["quantity"].is_array());
+
+    // Ten concurrent sales of one unit against five in stock.
+    let sales = (0..10).map(|_| {
+        app.server
+            .post("/product/stock")
+            .authorization_bearer(&token)
+            .json(&json!({ "product_id": product_id, "quantity": -1, "reason": "sale" }))
+            .into_future()
+    });
+    let mut sold = 0;
+    for response in join_all(sales).await {
+        match response.status_code() {
+            StatusCode::OK => sold += 1,
+            status => assert_eq!(status, StatusCode::CONFLICT),
+        }
+    }
+    assert_eq!(sold, 5);
+
+    let ledger: Value = app
+        .server
+        .get("/product/stock")
+        .authorization_bearer(&token)
+        .add_query_param("product_id", product_id)
+        .await
+        .json();
+    assert_eq!(ledger["total"], 6);
+    assert_eq!(ledger["items"][0]["stock_after"], 0);
+}
+
+#[tokio::test]
+async fn stock_never_overflows() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    let product_id = product(&app, &token).await;
+    adjust(&app, &token, product_id, i32::MAX, "restock").await;
+
+    let body = adjust(&app, &token, product_id, i32::MAX, "restock").await;
+    assert_eq!(body["code"], "stock_limit_exceeded");
+    assert_eq!(body["details"]["stock"], i32::MAX);
+    let body = adjust(&app, &token, product_id, 1, "restock").await;
+    assert_eq!(body["code"], "stock_limit_exceeded");
+
+    let body = adjust(&app, &token, product_id, i32::MIN, "sale").await;
+    assert_eq!(body["code"], "insufficient_stock");
+    let movement = adjust(&app, &token, product_id, -i32::MAX, "sale").await;
+    assert_eq!(movement["stock_after"], 0);
+}
+
+#[tokio::test]
+async fn inventory_requires_editor() {
+    let app = spawn_app().await;
+    let (_, editor) = app.user_with_role("editor", Role::Editor).await;
+    let (_, user) = app.user_with_role("user", Role::User).await;
+    let product_id = product(&app, &editor).await;
+
+    app.server
+        .post("/product/stock")
+        .authorization_bearer(&user)
+        .json(&json!({ "product_id": product_id, "quantity": 1, "reason": "restock" }))
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    app.server
+        .get("/product/low-stock")
+        .authorization_bearer(&user)
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
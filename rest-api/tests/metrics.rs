@@ -0,0 +1,33 @@
+mod common;
+
+use common::{spawn_app, PASSWORD};
+use serde_json::json;
+
+#[tokio::test]
+async fn metrics_cover_requests_logins_queries_and_hashing() {
+    let app = spawn_app().await;
+
+    app.signup("alice").await;
+    app.login("alice").await;
+    app.server
+        .post("/auth/login")
+        .json(&json!({ "username": "alice", "password": format!("{}x", PASSWORD) }))
+        .await
+        .assert_status_unauthorized();
+
+    let response = app.server.get("/metrics").await;
+    response.assert_status_ok();
+    let body = response.text();
+
+    for expected in [
+        r#"http_requests_total{method="POST",path="/auth/login",status="401"}"#,
+        r#"http_request_duration_seconds_bucket{method="POST",path="/auth/signup",status="200""#,
+        r#"http_requests_in_flight{method="GET",path="/metrics"}"#,
+        r#"auth_login_attempts_total{result="success"}"#,
+        r#"auth_login_attempts_total{result="failure"}"#,
+        r#"db_query_duration_seconds_bucket{operation="insert",failed="false""#,
+        r#"password_hash_duration_seconds_count{operation="verify"}"#,
+    ] {
+        assert!(body.contains(expected), "missing {} in\n{}", expected, body);
+    }
+}
@@ -0,0 +1,262 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_project::entities::sea_orm_active_enums::Role;
+use serde_json::{json, Value};
+
+use common::{spawn_app, TestApp};
+
+/// Creates two products with ten units each and returns their ids.
+async fn stocked_products(app: &TestApp, token: &str) -> (i64, i64) {
+    app.server
+        .post("/category")
+        .authorization_bearer(token)
+        .json(&json!({ "name": "books" }))
+        .await
+        .assert_status_ok();
+
+    let mut ids = Vec::new();
+    for (title, price) in [("Rust in Action", 40), ("Zero to Production", 35)] {
+        let product: Value = app
+            .server
+            .post("/product")
+            .authorization_bearer(token)
+            .json(&json!({ "title": title, "price": price, "category": "books" }))
+            .await
+            .json();
+        let id = product["id"].as_i64().unwrap();
+        app.server
+            .post("/product/stock")
+            .authorization_bearer(token)
+            .json(&json!({ "product_id": id, "quantity": 10, "reason": "restock" }))
+            .await
+            .assert_status_ok();
+        ids.push(id);
+    }
+
+    (ids[0], ids[1])
+}
+
+async fn set_cart(app: &TestApp, token: &str, product_id: i64, quantity: i32) -> Value {
+    app.server
+        .put("/cart")
+        .authorization_bearer(token)
+        .json(&json!({ "product_id": product_id, "quantity": quantity }))
+        .await
+        .json()
+}
+
+async fn set_status(app: &TestApp, token: &str, order_id: &Value, status: &str) -> Value {
+    app.server
+        .put("/orders/status")
+        .authorization_bearer(token)
+        .json(&json!({ "id": order_id, "status": status }))
+        .await
+        .json()
+}
+
+async fn stock(app: &TestApp, token: &str, product_id: i64) -> Value {
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(token)
+        .add_query_param("id", product_id)
+        .await
+        .json();
+    page["items"][0]["stock"].clone()
+}
+
+#[tokio::test]
+async fn checkout_snapshots_prices_and_takes_stock() {
+    let app = spawn_app().await;
+    let (_, editor) = app.user_with_role("editor", Role::Editor).await;
+    let (_, user) = app.user_with_role("user", Role::User).await;
+    let (rust, zero) = stocked_products(&app, &editor).await;
+
+    set_cart(&app, &user, rust, 1).await;
+    set_cart(&app, &user, zero, 5).await;
+    let cart = set_cart(&app, &user, rust, 2).await;
+    assert_eq!(cart["items"].as_array().unwrap().len(), 2);
+    assert_eq!(cart["total"], 2 * 40 + 5 * 35);
+
+    let order: Value = app
+        .server
+        .post("/orders")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    assert_eq!(order["status"], "pending");
+    assert_eq!(order["total"], 255);
+    assert_eq!(order["lines"][0]["title"], "Rust in Action");
+    assert_eq!(order["lines"][0]["unit_price"], 40);
+
+    // Later price changes don't touch placed orders.
+    app.server
+        .put("/product")
+        .authorization_bearer(&editor)
+        .json(&json!({ "id": rust, "price": 99 }))
+        .await
+        .assert_status_ok();
+
+    let history: Value = app
+        .server
+        .get("/orders")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    assert_eq!(history["total"], 1);
+    assert_eq!(history["items"][0]["lines"][0]["unit_price"], 40);
+    assert_eq!(history["items"][0]["total"], 255);
+
+    assert_eq!(stock(&app, &editor, rust).await, 8);
+    assert_eq!(stock(&app, &editor, zero).await, 5);
+    let cart: Value = app
+        .server
+        .get("/cart")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    assert_eq!(cart["items"], json!([]));
+
+    let body: Value = app
+        .server
+        .post("/orders")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    assert_eq!(body["code"], "cart_empty");
+}
+
+#[tokio::test]
+async fn failed_checkout_changes_nothing() {
+    let app = spawn_app().await;
+    let (_, editor) = app.user_with_role("editor", Role::Editor).await;
+    let (_, user) = app.user_with_role("user", Role::User).await;
+    let (rust, zero) = stocked_products(&app, &editor).await;
+
+    set_cart(&app, &user, rust, 3).await;
+    set_cart(&app, &user, zero, 11).await;
+
+    let body: Value = app
+        .server
+        .post("/orders")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    assert_eq!(body["code"], "insufficient_stock");
+    assert_eq!(body["details"]["product_id"], zero);
+
+    assert_eq!(stock(&app, &editor, rust).await, 10);
+    let history: Value = app
+        .server
+        .get("/orders")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    assert_eq!(history["total"], 0);
+    let cart: Value = app
+        .server
+        .get("/cart")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    assert_eq!(cart["items"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn status_transitions() {
+    let app = spawn_app().await;
+    let (_, editor) = app.user_with_role("editor", Role::Editor).await;
+    let (_, user) = app.user_with_role("user", Role::User).await;
+    let (rust, _) = stocked_products(&app, &editor).await;
+
+    set_cart(&app, &user, rust, 4).await;
+    let order: Value = app
+        .server
+        .post("/orders")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    let id = &order["id"];
+
+    let body = set_status(&app, &user, id, "paid").await;
+    assert_eq!(body["code"], "forbidden");
+
+    assert_eq!(
+        set_status(&app, &editor, id, "paid").await["status"],
+        "paid"
+    );
+    let body = set_status(&app, &editor, id, "pending").await;
+    assert_eq!(body["code"], "invalid_transition");
+
+    // Cancelling puts the stock back.
+    assert_eq!(stock(&app, &editor, rust).await, 6);
+    assert_eq!(
+        set_status(&app, &editor, id, "cancelled").await["status"],
+        "cancelled"
+    );
+    assert_eq!(stock(&app, &editor, rust).await, 10);
+
+    let body = set_status(&app, &editor, id, "shipped").await;
+    assert_eq!(body["code"], "invalid_transition");
+
+    // Owners may cancel their own pending orders.
+    set_cart(&app, &user, rust, 1).await;
+    let order: Value = app
+        .server
+        .post("/orders")
+        .authorization_bearer(&user)
+        .await
+        .json();
+    let cancelled = set_status(&app, &user, &order["id"], "cancelled").await;
+    assert_eq!(cancelled["status"], "cancelled");
+    assert_eq!(stock(&app, &editor, rust).await, 10);
+}
+
+#[tokio::test]
+async fn orders_are_private() {
+    let app = spawn_app().await;
+    let (_, editor) = app.user_with_role("editor", Role::Editor).await;
+    let (alice_id, alice) = app.user_with_role("alice", Role::User).await;
+    let (_, bob) = app.user_with_role("bob", Role::User).await;
+    let (rust, _) = stocked_products(&app, &editor).await;
+
+    set_cart(&app, &alice, rust, 1).await;
+    let order: Value = app
+        .server
+        .post("/orders")
+        .authorization_bearer(&alice)
+        .await
+        .json();
+
+    let bobs: Value = app
+        .server
+        .get("/orders")
+        .authorization_bearer(&bob)
+        .await
+        .json();
+    assert_eq!(bobs["total"], 0);
+    app.server
+        .get("/orders")
+        .authorization_bearer(&bob)
+        .add_query_param("user_id", alice_id)
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    app.server
+        .put("/orders/status")
+        .authorization_bearer(&bob)
+        .json(&json!({ "id": order["id"], "status": "cancelled" }))
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let alices: Value = app
+        .server
+        .get("/orders")
+        .authorization_bearer(&editor)
+        .add_query_param("user_id", alice_id)
+        .await
+        .json();
+    assert_eq!(alices["total"], 1);
+}
@@ -0,0 +1,216 @@
+mod common;
+
+use axum_project::entities::sea_orm_active_enums::Role;
+use serde_json::{json, Value};
+
+use common::{spawn_app, TestApp};
+
+async fn seed(app: &TestApp, token: &str) {
+    for category in ["books", "games"] {
+        app.server
+            .post("/category")
+            .authorization_bearer(token)
+            .json(&json!({ "name": category }))
+            .await
+            .assert_status_ok();
+    }
+
+    for (title, price, category) in [
+        ("Rust in Action", 40, "books"),
+        ("Zero to Production", 35, "books"),
+        ("Chess Set", 25, "games"),
+    ] {
+        app.server
+            .post("/product")
+            .authorization_bearer(token)
+            .json(&json!({ "title": title, "price": price, "category": category }))
+            .await
+            .assert_status_ok();
+    }
+}
+
+#[tokio::test]
+async fn editor_creates_updates_and_deletes_product() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    seed(&app, &token).await;
+
+    let product: Value = app
+        .server
+        .put("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": 1, "price": 45 }))
+        .await
+        .json();
+    assert_eq!(product["price"], 45);
+    assert_eq!(product["title"], "Rust in Action");
+
+    app.server
+        .delete("/product")
+        .authorization_bearer(&token)
+        .add_query_param("id", 1)
+        .await
+        .assert_status_ok();
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(page["total"], 2);
+}
+
+#[tokio::test]
+async fn filters_combine() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    seed(&app, &token).await;
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .add_query_param("category", "books,games")
+        .add_query_param("min_price", 30)
+        .add_query_param("sort", "price:asc")
+        .await
+        .json();
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["items"][0]["title"], "Zero to Production");
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .add_query_param("q", "RUST")
+        .await
+        .json();
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["title"], "Rust in Action");
+}
+
+#[tokio::test]
+async fn product_with_unknown_category_is_rejected() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    let response = app
+        .server
+        .post("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "title": "Orphan", "price": 1, "category": "nope" }))
+        .await;
+
+    response.assert_status_unprocessable_entity();
+    let fields = &response.json::<Value>()["details"]["fields"];
+    assert_eq!(fields["category"][0]["code"], "unknown_category");
+}
+
+#[tokio::test]
+async fn invalid_product_payload_lists_fields() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+
+    let response = app
+        .server
+        .post("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "title": "", "price": -5 }))
+        .await;
+
+    response.assert_status_unprocessable_entity();
+    let fields = &response.json::<Value>()["details"]["fields"];
+    assert!(fields["title"].is_array());
+    assert!(fields["price"].is_array());
+    assert!(fields["category"].is_array());
+}
+
+#[tokio::test]
+async fn plain_user_cannot_write_products() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("alice", Role::User).await;
+
+    app.server
+        .post("/product")
+        .authorization_bearer(&token)
+        .json(&json!({ "title": "Nope", "price": 1, "category": "books" }))
+        .await
+        .assert_status_forbidden();
+
+    app.server
+        .get("/product")
+        .add_query_param("price", "abc")
+        .authorization_bearer(&token)
+        .await
+        .assert_status_bad_request();
+}
+
+/// Full-text search only runs on Postgres, so this needs a migrated database
+/// in `TEST_DATABASE_URL` and is skipped without one. Names are random, so
+/// it can share that database.
+#[tokio::test]
+async fn full_text_search_on_postgres_matches_stems_and_ranks() {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set, skipping");
+        return;
+    };
+    let mut config = common::test_config();
+    config.database.url = url;
+    let app = common::spawn_app_with(config).await;
+
+    // Letters only, so the parser keeps it a single plain word.
+    let tag: String = (0..12)
+        .map(|_| (b'a' + rand::random::<u8>() % 26) as char)
+        .collect();
+    let (_, token) = app
+        .user_with_role(&format!("editor_{}", tag), Role::Editor)
+        .await;
+    let category: Value = app
+        .server
+        .post("/category")
+        .authorization_bearer(&token)
+        .json(&json!({ "name": format!("fts {}", tag) }))
+        .await
+        .json();
+
+    for title in [
+        format!("{} running shoes", tag),
+        format!("{} running shorts for running", tag),
+        format!("{} chess clock for runners", tag),
+        format!("{} hiking boots", tag),
+    ] {
+        app.server
+            .post("/product")
+            .authorization_bearer(&token)
+            .json(&json!({ "title": title, "price": 10, "category": category["slug"] }))
+            .await
+            .assert_status_ok();
+    }
+
+    let search = |q: String| {
+        app.server
+            .get("/product")
+            .authorization_bearer(&token)
+            .add_query_param("q", q)
+    };
+
+    // "run" matches "running" and "runners"; `-chess` excludes; the title
+    // mentioning it twice ranks first.
+    let page: Value = search(format!("{} run -chess", tag)).await.json();
+    assert_eq!(page["total"], 2);
+    assert_eq!(
+        page["items"][0]["title"],
+        format!("{} running shorts for running", tag)
+    );
+    assert_eq!(page["items"][1]["title"], format!("{} running shoes", tag));
+
+    let page: Value = search(format!("\"{} hiking boots\"", tag)).await.json();
+    assert_eq!(page["total"], 1);
+
+    // The query is bound, never spliced into the SQL.
+    let page: Value = search(format!("{}'); drop table product; --", tag))
+        .await
+        .json();
+    assert_eq!(page["total"], 0);
+}
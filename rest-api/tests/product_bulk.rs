@@ -0,0 +1,178 @@
+mod common;
+
+use axum::body::Bytes;
+use axum_project::entities::sea_orm_active_enums::Role;
+use serde_json::{json, Value};
+
+use common::{spawn_app, TestApp};
+
+async fn seed(app: &TestApp, token: &str) {
+    for name in ["books", "games"] {
+        app.server
+            .post("/category")
+            .authorization_bearer(token)
+            .json(&json!({ "name": name }))
+            .await
+            .assert_status_ok();
+    }
+    app.server
+        .post("/product")
+        .authorization_bearer(token)
+        .json(&json!({ "title": "Dune", "price": 9, "category": "books" }))
+        .await
+        .assert_status_ok();
+}
+
+async fn import(
+    app: &TestApp,
+    token: &str,
+    content_type: &str,
+    body: &str,
+) -> axum_test::TestResponse {
+    app.server
+        .post("/product/import")
+        .authorization_bearer(token)
+        .content_type(content_type)
+        .bytes(Bytes::from(body.to_owned()))
+        .await
+}
+
+#[tokio::test]
+async fn csv_import_creates_and_updates_products() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    seed(&app, &token).await;
+
+    let csv = "id,title,price,category,stock\r\n\
+               1,Dune (2nd edition),12,books,99\r\n\
+               ,\"Catan, \"\"the\"\"\ngame\",30,games,\r\n";
+    let response = import(&app, &token, "text/csv", csv).await;
+    response.assert_status_ok();
+    let report: Value = response.json();
+    assert_eq!(report["rows"], 2);
+    assert_eq!(report["created"], 1);
+    assert_eq!(report["updated"], 1);
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(page["items"][0]["title"], "Dune (2nd edition)");
+    assert_eq!(page["items"][0]["stock"], 0);
+    assert_eq!(page["items"][1]["title"], "Catan, \"the\"\ngame");
+
+    let response = app
+        .server
+        .post("/product/import")
+        .authorization_bearer(&token)
+        .add_query_param("dry_run", true)
+        .content_type("text/csv")
+        .bytes(Bytes::from("title,price,category\nBad,cheap,books\n"))
+        .await;
+    let report: Value = response.json();
+    assert_eq!(
+        report["errors"][0],
+        json!({ "line": 2, "message": "price: invalid digit found in string" })
+    );
+}
+
+#[tokio::test]
+async fn failed_rows_abort_the_import_and_dry_run_reports_them() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    seed(&app, &token).await;
+
+    let ndjson = concat!(
+        "{\"title\": \"Chess\", \"price\": 5, \"category\": \"games\"}\n",
+        "{\"title\": \"\", \"price\": -1, \"category\": \"games\"}\n",
+        "not json\n",
+        "{\"title\": \"Go\", \"price\": 5, \"category\": \"nope\"}\n",
+        "{\"id\": 42, \"title\": \"Ghost\", \"price\": 5, \"category\": \"games\"}\n",
+    );
+
+    let response = import(&app, &token, "application/x-ndjson", ndjson).await;
+    response.assert_status_unprocessable_entity();
+    let body: Value = response.json();
+    assert_eq!(body["code"], "import_failed");
+    assert_eq!(body["details"]["failed"], 4);
+
+    let response = app
+        .server
+        .post("/product/import")
+        .authorization_bearer(&token)
+        .add_query_param("dry_run", true)
+        .content_type("application/x-ndjson")
+        .bytes(Bytes::from(ndjson))
+        .await;
+    response.assert_status_ok();
+    let report: Value = response.json();
+    assert_eq!(report["created"], 1);
+    let errors = report["errors"].as_array().unwrap();
+    assert_eq!(
+        errors
+            .iter()
+            .map(|error| error["line"].clone())
+            .collect::<Vec<_>>(),
+        [json!(2), json!(3), json!(4), json!(5)]
+    );
+    assert!(errors[0]["fields"]["price"].is_array());
+    assert_eq!(
+        errors[2]["fields"]["category"][0]["code"],
+        "unknown_category"
+    );
+    assert_eq!(errors[3]["fields"]["id"][0]["code"], "unknown_product");
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(page["total"], 1);
+}
+
+#[tokio::test]
+async fn export_streams_filtered_products() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    seed(&app, &token).await;
+    import(
+        &app,
+        &token,
+        "text/csv",
+        "title,price,category\nChess,5,games\n",
+    )
+    .await
+    .assert_status_ok();
+
+    let response = app
+        .server
+        .get("/product/export")
+        .authorization_bearer(&token)
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.text(),
+        "id,title,price,category,stock\n1,Dune,9,books,0\n2,Chess,5,games,0\n"
+    );
+
+    let response = app
+        .server
+        .get("/product/export")
+        .authorization_bearer(&token)
+        .add_query_param("format", "ndjson")
+        .add_query_param("category", "games")
+        .await;
+    assert_eq!(response.header("content-type"), "application/x-ndjson");
+    let rows: Vec<Value> = response
+        .text()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        rows,
+        [json!({ "id": 2, "title": "Chess", "price": 5, "category": "games", "stock": 0 })]
+    );
+}
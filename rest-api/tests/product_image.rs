@@ -0,0 +1,191 @@
+mod common;
+
+use std::io::Cursor;
+
+use axum::http::StatusCode;
+use axum_project::entities::sea_orm_active_enums::Role;
+use axum_test::multipart::{MultipartForm, Part};
+use image::{DynamicImage, ImageFormat};
+use serde_json::{json, Value};
+
+use common::{spawn_app, spawn_app_with, test_config, TestApp};
+
+fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    DynamicImage::new_rgb8(width, height)
+        .write_to(&mut Cursor::new(&mut bytes), format)
+        .unwrap();
+    bytes
+}
+
+fn image_part(bytes: Vec<u8>, content_type: &str) -> Part {
+    Part::bytes(bytes)
+        .file_name("upload")
+        .mime_type(content_type)
+}
+
+async fn product(app: &TestApp, token: &str) -> i64 {
+    app.server
+        .post("/category")
+        .authorization_bearer(token)
+        .json(&json!({ "name": "books" }))
+        .await
+        .assert_status_ok();
+
+    app.server
+        .post("/product")
+        .authorization_bearer(token)
+        .json(&json!({ "title": "Rust in Action", "price": 40, "category": "books" }))
+        .await
+        .json::<Value>()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+async fn upload(app: &TestApp, token: &str, product_id: i64, form: MultipartForm) -> Value {
+    app.server
+        .post("/product/images")
+        .authorization_bearer(token)
+        .add_query_param("product_id", product_id)
+        .multipart(form)
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn uploaded_images_are_listed_served_and_deleted() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    let product_id = product(&app, &token).await;
+
+    let png = encode(600, 300, ImageFormat::Png);
+    let form = MultipartForm::new()
+        .add_part("image", image_part(png.clone(), "image/png"))
+        .add_part(
+            "image",
+            image_part(encode(40, 80, ImageFormat::Jpeg), "image/jpeg"),
+        );
+    let images = upload(&app, &token, product_id, form).await;
+    assert_eq!(images.as_array().unwrap().len(), 2);
+    assert_eq!(images[0]["width"], 600);
+    assert_eq!(images[1]["content_type"], "image/jpeg");
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(page["items"][0]["title"], "Rust in Action");
+    assert_eq!(page["items"][0]["images"], images);
+
+    let url = images[0]["url"].as_str().unwrap();
+    assert!(url.starts_with("/media/products/"));
+    assert_eq!(app.server.get(url).await.as_bytes().to_vec(), png);
+
+    let thumbnail = app
+        .server
+        .get(images[0]["thumbnail_url"].as_str().unwrap())
+        .await
+        .as_bytes()
+        .to_vec();
+    let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+
+    app.server
+        .delete("/product/images")
+        .authorization_bearer(&token)
+        .add_query_param("id", images[0]["id"].as_i64().unwrap())
+        .await
+        .assert_status_ok();
+    app.server
+        .get(url)
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // Products are only soft-deleted, so their images stay for a restore.
+    let remaining = images[1]["url"].as_str().unwrap();
+    app.server
+        .delete("/product")
+        .authorization_bearer(&token)
+        .add_query_param("id", product_id)
+        .await
+        .assert_status_ok();
+    app.server.get(remaining).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn uploads_are_validated() {
+    let mut config = test_config();
+    config.storage.max_image_bytes = 4096;
+    let app = spawn_app_with(config).await;
+    let (_, token) = app.user_with_role("editor", Role::Editor).await;
+    let product_id = product(&app, &token).await;
+
+    // PNG bytes declared as JPEG.
+    let form = MultipartForm::new().add_part(
+        "image",
+        image_part(encode(8, 8, ImageFormat::Png), "image/jpeg"),
+    );
+    let body = upload(&app, &token, product_id, form).await;
+    assert_eq!(body["code"], "unsupported_media_type");
+
+    let form = MultipartForm::new().add_part("image", image_part(b"GIF89a".to_vec(), "image/gif"));
+    let body = upload(&app, &token, product_id, form).await;
+    assert_eq!(body["code"], "unsupported_media_type");
+
+    let form = MultipartForm::new().add_part("image", image_part(vec![0; 8192], "image/png"));
+    let body = upload(&app, &token, product_id, form).await;
+    assert_eq!(body["code"], "image_too_large");
+
+    // A valid image alongside an invalid one saves neither.
+    let form = MultipartForm::new()
+        .add_part(
+            "image",
+            image_part(encode(8, 8, ImageFormat::Png), "image/png"),
+        )
+        .add_part("image", image_part(b"not a png".to_vec(), "image/png"));
+    let body = upload(&app, &token, product_id, form).await;
+    assert_eq!(body["code"], "unsupported_media_type");
+
+    let form = MultipartForm::new().add_text("caption", "hello");
+    let body = upload(&app, &token, product_id, form).await;
+    assert_eq!(body["code"], "invalid_multipart");
+
+    let form = MultipartForm::new().add_part(
+        "image",
+        image_part(encode(8, 8, ImageFormat::Png), "image/png"),
+    );
+    let body = upload(&app, &token, product_id + 1, form).await;
+    assert_eq!(body["code"], "not_found");
+
+    let page: Value = app
+        .server
+        .get("/product")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(page["items"][0]["images"], json!([]));
+}
+
+#[tokio::test]
+async fn upload_requires_editor() {
+    let app = spawn_app().await;
+    let (_, editor) = app.user_with_role("editor", Role::Editor).await;
+    let (_, user) = app.user_with_role("user", Role::User).await;
+    let product_id = product(&app, &editor).await;
+
+    let form = MultipartForm::new().add_part(
+        "image",
+        image_part(encode(8, 8, ImageFormat::Png), "image/png"),
+    );
+    app.server
+        .post("/product/images")
+        .authorization_bearer(&user)
+        .add_query_param("product_id", product_id)
+        .multipart(form)
+        .expect_failure()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_project::{
+    config::{Config, Quota},
+    entities::sea_orm_active_enums::Role,
+};
+use common::{spawn_app_with, test_config};
+use serde_json::{json, Value};
+
+fn limited_config() -> Config {
+    let mut config = test_config();
+    config.rate_limit.enabled = true;
+    config.rate_limit.auth = Quota {
+        burst: 3,
+        per_minute: 1,
+    };
+    config.rate_limit.api = Quota {
+        burst: 2,
+        per_minute: 1,
+    };
+    config
+}
+
+#[tokio::test]
+async fn auth_routes_are_limited_per_client_ip() {
+    let app = spawn_app_with(limited_config()).await;
+
+    for remaining in ["2", "1", "0"] {
+        let response = app
+            .server
+            .post("/auth/login")
+            .json(&json!({ "username": "nobody", "password": "password123" }))
+            .await;
+        response.assert_status_unauthorized();
+        assert_eq!(response.header("ratelimit-limit"), "3");
+        assert_eq!(response.header("ratelimit-remaining"), remaining);
+    }
+
+    let response = app
+        .server
+        .post("/auth/signup")
+        .json(&json!({ "username": "alice", "password": "password123" }))
+        .await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.json::<Value>()["code"], "rate_limited");
+    assert!(response.headers().contains_key("retry-after"));
+    assert!(response.headers().contains_key("ratelimit-reset"));
+}
+
+#[tokio::test]
+async fn api_routes_are_limited_per_user() {
+    let mut config = limited_config();
+    config.rate_limit.auth.burst = 10;
+    let app = spawn_app_with(config).await;
+    let (_, alice) = app.user_with_role("alice", Role::User).await;
+    let (_, bob) = app.user_with_role("bob", Role::User).await;
+
+    for _ in 0..2 {
+        app.server
+            .get("/users")
+            .authorization_bearer(&alice)
+            .await
+            .assert_status_ok();
+    }
+    app.server
+        .get("/users")
+        .authorization_bearer(&alice)
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+    app.server
+        .get("/users")
+        .authorization_bearer(&bob)
+        .await
+        .assert_status_ok();
+    // Probes are never limited.
+    for _ in 0..5 {
+        app.server.get("/healthz").await.assert_status_ok();
+    }
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::path::PathBuf;
+
+use axum::http::StatusCode;
+use axum_project::{
+    config::{Config, JwtAlgorithm, JwtKeyConfig},
+    entities::sea_orm_active_enums::Role,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, jwk::JwkSet, Algorithm, DecodingKey, EncodingKey, Header,
+    Validation,
+};
+use serde_json::{json, Value};
+
+use common::{spawn_app_with, test_config};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/jwt")
+        .join(name)
+}
+
+fn key(kid: &str, algorithm: JwtAlgorithm, file: &str, private: bool) -> JwtKeyConfig {
+    JwtKeyConfig {
+        kid: kid.to_owned(),
+        algorithm,
+        public_key: fixture(&format!("{}.pub.pem", file)),
+        private_key: private.then(|| fixture(&format!("{}.pem", file))),
+    }
+}
+
+/// Signs with `rsa`, still accepts tokens signed with `rsa-old`, and has no
+/// HS256 secret.
+fn rotated_config() -> Config {
+    let mut config = test_config();
+    config.auth.secret_key.clear();
+    config.auth.jwt.signing_kid = "new".to_owned();
+    config.auth.jwt.keys = vec![
+        key("new", JwtAlgorithm::Rs256, "rsa", true),
+        key("old", JwtAlgorithm::Rs256, "rsa-old", false),
+    ];
+    config
+}
+
+fn token_with(header: Header, key: &EncodingKey, sub: i32) -> String {
+    let claims = json!({
+        "exp": chrono::Utc::now().timestamp() + 60,
+        "sub": sub,
+        "username": "alice",
+        "role": "user",
+    });
+    encode(&header, &claims, key).unwrap()
+}
+
+#[tokio::test]
+async fn tokens_are_signed_with_the_active_key_and_verifiable_from_jwks() {
+    let config = rotated_config();
+    assert!(config.validate().is_ok());
+    let app = spawn_app_with(config).await;
+    let (id, token) = app.user_with_role("alice", Role::User).await;
+
+    let header = decode_header(&token).unwrap();
+    assert_eq!(header.alg, Algorithm::RS256);
+    assert_eq!(header.kid.as_deref(), Some("new"));
+
+    let response = app.server.get("/.well-known/jwks.json").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("cache-control"), "public, max-age=300");
+    let jwks: JwkSet = response.json();
+    assert_eq!(jwks.keys.len(), 2);
+    let jwk = jwks.find("new").unwrap();
+    let claims: Value = decode(
+        &token,
+        &DecodingKey::from_jwk(jwk).unwrap(),
+        &Validation::new(Algorithm::RS256),
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(claims["sub"], id);
+    let published: Value = response.json();
+    assert!(published["keys"][0].get("d").is_none());
+
+    // A token signed with the retired key still works until it expires.
+    let mut old = Header::new(Algorithm::RS256);
+    old.kid = Some("old".to_owned());
+    let old_key =
+        EncodingKey::from_rsa_pem(&std::fs::read(fixture("rsa-old.pem")).unwrap()).unwrap();
+    app.server
+        .get("/users")
+        .authorization_bearer(token_with(old, &old_key, id))
+        .await
+        .assert_status_ok();
+
+    // Unknown key ids, a key's wrong algorithm and HS256 without a secret
+    // are all rejected.
+    let mut unknown = Header::new(Algorithm::RS256);
+    unknown.kid = Some("gone".to_owned());
+    let mut confused = Header::new(Algorithm::HS256);
+    confused.kid = Some("new".to_owned());
+    let secret = EncodingKey::from_secret(b"test-secret");
+    for token in [
+        token_with(unknown, &old_key, id),
+        token_with(confused, &secret, id),
+        token_with(Header::new(Algorithm::HS256), &secret, id),
+    ] {
+        app.server
+            .get("/users")
+            .authorization_bearer(token)
+            .expect_failure()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn eddsa_keys_sign_and_are_published() {
+    let mut config = test_config();
+    config.auth.jwt.signing_kid = "ed".to_owned();
+    config.auth.jwt.keys = vec![key("ed", JwtAlgorithm::EdDsa, "ed25519", true)];
+    let app = spawn_app_with(config).await;
+    let (_, token) = app.user_with_role("alice", Role::User).await;
+
+    assert_eq!(decode_header(&token).unwrap().alg, Algorithm::EdDSA);
+    app.server
+        .get("/users")
+        .authorization_bearer(&token)
+        .await
+        .assert_status_ok();
+
+    let jwks: Value = app.server.get("/.well-known/jwks.json").await.json();
+    assert_eq!(jwks["keys"][0]["kty"], "OKP");
+    assert_eq!(jwks["keys"][0]["crv"], "Ed25519");
+    assert_eq!(jwks["keys"][0]["alg"], "EdDSA");
+    assert_eq!(jwks["keys"][0]["kid"], "ed");
+}
+
+#[test]
+fn validate_rejects_unusable_keys() {
+    let mut config = rotated_config();
+    config.auth.jwt.signing_kid = "old".to_owned();
+    assert!(config
+        .validate()
+        .unwrap_err()
+        .to_string()
+        .contains("signing key `old` has no private_key"));
+
+    let mut config = rotated_config();
+    config.auth.jwt.keys[0].private_key = Some(fixture("rsa-old.pem"));
+    assert!(config
+        .validate()
+        .unwrap_err()
+        .to_string()
+        .contains("private and public key of `new` don't match"));
+
+    let mut config = rotated_config();
+    config.auth.jwt.keys[1].algorithm = JwtAlgorithm::EdDsa;
+    assert!(config
+        .validate()
+        .unwrap_err()
+        .to_string()
+        .contains("auth.jwt"));
+}
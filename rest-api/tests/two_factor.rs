@@ -0,0 +1,189 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_project::entities::sea_orm_active_enums::Role;
+use serde_json::{json, Value};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use common::{spawn_app, TestApp};
+
+/// The code an authenticator app would show `offset_secs` from now.
+fn code(secret: &str, offset_secs: i64) -> String {
+    let totp = TOTP::new_unchecked(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_owned()).to_bytes().unwrap(),
+        None,
+        String::new(),
+    );
+    let now = chrono::Utc::now().timestamp() + offset_secs;
+    totp.generate(now as u64)
+}
+
+/// Enrolls the user and returns the secret and recovery codes. Confirms with
+/// the previous step's code, so the current one is still unused.
+async fn enroll(app: &TestApp, token: &str) -> (String, Vec<String>) {
+    let enrollment: Value = app
+        .server
+        .post("/users/totp")
+        .authorization_bearer(token)
+        .await
+        .json();
+    let secret = enrollment["secret"].as_str().unwrap().to_owned();
+    assert!(enrollment["otpauth_uri"]
+        .as_str()
+        .unwrap()
+        .starts_with("otpauth://totp/"));
+
+    let response = app
+        .server
+        .post("/users/totp/confirm")
+        .authorization_bearer(token)
+        .json(&json!({ "code": code(&secret, -30) }))
+        .await;
+    response.assert_status_ok();
+    let codes = response.json::<Value>()["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_owned())
+        .collect();
+
+    (secret, codes)
+}
+
+async fn challenge(app: &TestApp, username: &str) -> String {
+    let response: Value = app.login(username).await;
+    assert_eq!(response["two_factor_required"], true);
+    assert!(response.get("access_token").is_none());
+    response["challenge_token"].as_str().unwrap().to_owned()
+}
+
+async fn login_totp(app: &TestApp, challenge: &str, code: &str) -> axum_test::TestResponse {
+    app.server
+        .post("/auth/login/totp")
+        .json(&json!({ "challenge_token": challenge, "code": code }))
+        .await
+}
+
+#[tokio::test]
+async fn login_needs_a_single_use_code_once_enrolled() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("admin", Role::Admin).await;
+
+    // An unconfirmed enrollment doesn't change how logins work.
+    let enrollment: Value = app
+        .server
+        .post("/users/totp")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    let response = app
+        .server
+        .post("/users/totp/confirm")
+        .authorization_bearer(&token)
+        .json(&json!({ "code": code(enrollment["secret"].as_str().unwrap(), 3600) }))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(response.json::<Value>()["code"], "invalid_code");
+    assert!(app.login("admin").await["access_token"].is_string());
+
+    let (secret, recovery_codes) = enroll(&app, &token).await;
+    assert_eq!(recovery_codes.len(), 10);
+
+    let challenge_token = challenge(&app, "admin").await;
+    // The challenge is no access token.
+    app.server
+        .get("/users")
+        .authorization_bearer(&challenge_token)
+        .await
+        .assert_status_unauthorized();
+
+    let response = login_totp(&app, &challenge_token, "not-a-code").await;
+    response.assert_status_unauthorized();
+    assert_eq!(response.json::<Value>()["code"], "invalid_code");
+
+    let current = code(&secret, 0);
+    let response = login_totp(&app, &challenge_token, &current).await;
+    response.assert_status_ok();
+    let access_token = response.json::<Value>()["access_token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    app.server
+        .get("/users")
+        .authorization_bearer(&access_token)
+        .await
+        .assert_status_ok();
+
+    // Neither the challenge nor the code can be used again.
+    let response = login_totp(&app, &challenge_token, &current).await;
+    assert_eq!(response.json::<Value>()["code"], "invalid_challenge");
+    let response = login_totp(&app, &challenge(&app, "admin").await, &current).await;
+    assert_eq!(response.json::<Value>()["code"], "invalid_code");
+
+    // Recovery codes work once each, dashes and case aside.
+    let recovery = recovery_codes[0].to_uppercase().replace('-', " ");
+    login_totp(&app, &challenge(&app, "admin").await, &recovery)
+        .await
+        .assert_status_ok();
+    login_totp(&app, &challenge(&app, "admin").await, &recovery_codes[0])
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn disabling_needs_a_code_unless_an_admin_does_it_for_someone_else() {
+    let app = spawn_app().await;
+    let (alice, alice_token) = app.user_with_role("alice", Role::User).await;
+    let (_, bob_token) = app.user_with_role("bob", Role::User).await;
+    let (_, admin_token) = app.user_with_role("admin", Role::Admin).await;
+    let (_, recovery_codes) = enroll(&app, &alice_token).await;
+
+    let disable = |token: &str, body: Value| {
+        app.server
+            .post("/users/totp/disable")
+            .authorization_bearer(token)
+            .json(&body)
+    };
+
+    disable(&alice_token, json!({}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    disable(&bob_token, json!({ "id": alice }))
+        .await
+        .assert_status_forbidden();
+
+    // Regenerating voids the old codes.
+    let response = app
+        .server
+        .post("/users/totp/recovery-codes")
+        .authorization_bearer(&alice_token)
+        .json(&json!({ "code": recovery_codes[0] }))
+        .await;
+    response.assert_status_ok();
+    let fresh = response.json::<Value>()["recovery_codes"][0]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    disable(&alice_token, json!({ "code": recovery_codes[1] }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    disable(&admin_token, json!({ "id": alice }))
+        .await
+        .assert_status_ok();
+    assert!(app.login("alice").await["access_token"].is_string());
+    let response = disable(&alice_token, json!({ "code": fresh })).await;
+    response.assert_status(StatusCode::CONFLICT);
+    assert_eq!(response.json::<Value>()["code"], "totp_not_enabled");
+
+    // Alice re-enrolls with a new secret and turns it off herself.
+    let (secret, _) = enroll(&app, &alice_token).await;
+    disable(&alice_token, json!({ "code": code(&secret, 0) }))
+        .await
+        .assert_status_ok();
+    assert!(app.login("alice").await["access_token"].is_string());
+}
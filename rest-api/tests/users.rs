@@ -0,0 +1,150 @@
+mod common;
+
+use axum_project::entities::sea_orm_active_enums::Role;
+use serde_json::{json, Value};
+
+use common::spawn_app;
+
+#[tokio::test]
+async fn list_users_is_paginated() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("alice", Role::User).await;
+    app.signup("bob").await;
+    app.signup("carol").await;
+
+    let page: Value = app
+        .server
+        .get("/users")
+        .authorization_bearer(&token)
+        .add_query_param("per_page", 2)
+        .add_query_param("sort", "username:desc")
+        .await
+        .json();
+
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["next_page"], 2);
+    assert_eq!(page["items"][0]["username"], "carol");
+    assert_eq!(page["items"][1]["username"], "bob");
+}
+
+#[tokio::test]
+async fn pages_past_the_largest_offset_are_rejected() {
+    let app = spawn_app().await;
+    let (_, token) = app.user_with_role("alice", Role::User).await;
+
+    let list = |page: u64| {
+        app.server
+            .get("/users")
+            .authorization_bearer(&token)
+            .add_query_param("page", page)
+            .add_query_param("per_page", 100)
+    };
+
+    list(u64::MAX).await.assert_status_bad_request();
+    list(i64::MAX as u64 / 100 + 2)
+        .await
+        .assert_status_bad_request();
+
+    let page: Value = list(i64::MAX as u64 / 100).await.json();
+    assert_eq!(page["items"], json!([]));
+    assert_eq!(page["next_page"], Value::Null);
+}
+
+#[tokio::test]
+async fn user_can_update_self_but_not_others() {
+    let app = spawn_app().await;
+    let (alice, token) = app.user_with_role("alice", Role::User).await;
+    let (bob, _) = app.user_with_role("bob", Role::User).await;
+
+    let response = app
+        .server
+        .put("/users")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": alice, "username": "alice2" }))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["username"], "alice2");
+
+    app.server
+        .put("/users")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": bob, "username": "hacked" }))
+        .await
+        .assert_status_forbidden();
+
+    app.server
+        .put("/users")
+        .authorization_bearer(&token)
+        .json(&json!({ "id": alice, "role": "admin" }))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+async fn email_and_two_factor_state_are_private() {
+    let app = spawn_app().await;
+    let (alice, alice_token) = app.user_with_role("alice", Role::User).await;
+    let (_, bob_token) = app.user_with_role("bob", Role::User).await;
+    let (_, admin_token) = app.user_with_role("admin", Role::Admin).await;
+    app.server
+        .put("/users")
+        .authorization_bearer(&alice_token)
+        .json(&json!({ "id": alice, "email": "alice@example.com" }))
+        .await
+        .assert_status_ok();
+
+    let app = &app;
+    let alice_as_seen_by = |token: String| async move {
+        app.server
+            .get("/users")
+            .authorization_bearer(&token)
+            .add_query_param("id", alice)
+            .await
+            .json::<Value>()["items"][0]
+            .clone()
+    };
+
+    let user = alice_as_seen_by(bob_token).await;
+    assert_eq!(user["username"], "alice");
+    for field in ["email", "email_verified_at", "totp_enabled_at", "password"] {
+        assert!(user.get(field).is_none(), "{} is visible", field);
+    }
+
+    for token in [alice_token, admin_token] {
+        let user = alice_as_seen_by(token).await;
+        assert_eq!(user["email"], "alice@example.com");
+        assert_eq!(user["totp_enabled_at"], Value::Null);
+        assert!(user.get("password").is_none());
+    }
+}
+
+#[tokio::test]
+async fn admin_can_delete_other_users() {
+    let app = spawn_app().await;
+    let (_, admin) = app.user_with_role("admin", Role::Admin).await;
+    let (bob, bob_token) = app.user_with_role("bob", Role::User).await;
+    let (carol, _) = app.user_with_role("carol", Role::User).await;
+
+    app.server
+        .delete("/users")
+        .authorization_bearer(&bob_token)
+        .add_query_param("id", carol)
+        .await
+        .assert_status_forbidden();
+
+    app.server
+        .delete("/users")
+        .authorization_bearer(&admin)
+        .add_query_param("id", bob)
+        .await
+        .assert_status_ok();
+
+    let page: Value = app
+        .server
+        .get("/users")
+        .authorization_bearer(&admin)
+        .add_query_param("id", bob)
+        .await
+        .json();
+    assert_eq!(page["total"], 0);
+}
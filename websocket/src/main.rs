@@ -1,9 +1,9 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use axum::{
     body::Body,
     extract::{
-        ws::{Message, WebSocket},
+        ws::{close_code, CloseFrame, Message, WebSocket},
         State, WebSocketUpgrade,
     },
     http::{HeaderMap, Request, StatusCode},
@@ -17,30 +17,105 @@ use futures_util::{
     stream::{SplitSink, SplitStream, StreamExt},
 };
 
-use tokio::sync::{
-    broadcast::{self, Receiver, Sender},
-    Mutex,
+use tokio::{
+    signal,
+    sync::{
+        broadcast::{self, Receiver, Sender},
+        mpsc, oneshot, Mutex,
+    },
 };
 
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone)]
 struct AppState {
     broadcast_tx: Arc<Mutex<Sender<Message>>>,
+    // 열린 연결마다 하나씩 보유, 모두 drop 되면 종료 가능
+    connections: mpsc::Sender<()>,
 }
 
 #[tokio::main]
 async fn main() {
     let (tx, _) = broadcast::channel(32);
+    let (connections, mut closed) = mpsc::channel::<()>(1);
     let app = AppState {
         broadcast_tx: Arc::new(Mutex::new(tx)),
+        connections,
     };
+    let broadcast_tx = app.broadcast_tx.clone();
 
     let app = Router::new()
         .route("/ws", get(websocket_handler))
         .route_layer(middleware::from_fn(authenticate))
+        .route("/healthz", get(health))
+        .route("/readyz", get(health))
         .with_state(app);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let (signalled_tx, signalled_rx) = oneshot::channel();
+
+    let server = async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                shutdown_signal().await;
+                println!("Shutting down, closing websockets...");
+                // 모든 클라이언트에 close frame 전송
+                let _ = broadcast_tx
+                    .lock()
+                    .await
+                    .send(Message::Close(Some(CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "server shutting down".into(),
+                    })));
+                let _ = signalled_tx.send(());
+            })
+            .await
+            .unwrap();
+
+        // upgrade 된 연결은 serve 가 기다려주지 않음
+        closed.recv().await;
+    };
+
+    let deadline = async move {
+        if signalled_rx.await.is_ok() {
+            tokio::time::sleep(SHUTDOWN_TIMEOUT).await;
+        } else {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    tokio::select! {
+        _ = server => println!("Server stopped"),
+        _ = deadline => println!("Drain timeout elapsed, dropping remaining connections"),
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+async fn health() -> &'static str {
+    "ok"
 }
 
 async fn authenticate(
@@ -64,6 +139,7 @@ async fn websocket_handler(ws: WebSocketUpgrade, State(app): State<AppState>) ->
 }
 
 async fn handle_socket(ws: WebSocket, app: AppState) {
+    let _connection = app.connections;
     let (ws_tx, ws_rx) = ws.split();
     let ws_tx = Arc::new(Mutex::new(ws_tx));
 
@@ -96,7 +172,8 @@ async fn recv_broadcast(
     mut broadcast_rx: Receiver<Message>,
 ) {
     while let Ok(msg) = broadcast_rx.recv().await {
-        if client_tx.lock().await.send(msg).await.is_err() {
+        let closing = matches!(msg, Message::Close(_));
+        if client_tx.lock().await.send(msg).await.is_err() || closing {
             return; // 오류 발생 시 연결 해제
         }
     }